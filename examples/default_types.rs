@@ -23,6 +23,7 @@ impl Pluggable for Struct {}
 
 #[derive(Clone, Debug)]
 struct IntPlugin {
+    #[allow(dead_code)]
     field: i32
 }
 
@@ -0,0 +1,107 @@
+//! Resolving one plugin's key to another's cached value.
+
+use std::marker::PhantomData;
+
+use typemap::Key;
+
+use ExtensionStore;
+
+// How to reach the aliased key's value in a store of type `S`.
+pub struct Redirect<P: Key, S> {
+    pub contains: fn(&S) -> bool,
+    pub get: fn(&S) -> Option<&P::Value>,
+    pub get_mut: fn(&mut S) -> &mut P::Value,
+    pub insert: fn(&mut S, P::Value) -> &mut P::Value,
+    pub remove: fn(&mut S) -> Option<P::Value>
+}
+
+impl<P: Key, S> Clone for Redirect<P, S> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<P: Key, S> Copy for Redirect<P, S> {}
+
+pub struct Alias<P, S>(PhantomData<(P, S)>);
+
+impl<P: Key, S: 'static> Key for Alias<P, S> { type Value = Redirect<P, S>; }
+
+pub fn register<Old, New, S>(store: &mut S)
+where Old: Key, New: Key<Value = Old::Value>, S: ExtensionStore + 'static {
+    store.insert::<Alias<Old, S>>(Redirect {
+        contains: contains_aliased::<New, S>,
+        get: get_aliased::<New, S>,
+        get_mut: get_mut::<New, S>,
+        insert: insert_aliased::<New, S>,
+        remove: remove_aliased::<New, S>
+    });
+}
+
+pub fn redirect<P: Key, S: ExtensionStore + 'static>(store: &S) -> Option<Redirect<P, S>> {
+    store.get::<Alias<P, S>>().cloned()
+}
+
+// Reads the value cached for `P`, or for the key it is aliased to.
+pub fn get<P: Key, S: ExtensionStore + 'static>(store: &S) -> Option<&P::Value> {
+    match redirect::<P, S>(store) {
+        Some(alias) => (alias.get)(store),
+        None => store.get::<P>()
+    }
+}
+
+// Whether a value is cached for `P`, or for the key it is aliased to.
+pub fn contains<P: Key, S: ExtensionStore + 'static>(store: &S) -> bool {
+    match redirect::<P, S>(store) {
+        Some(alias) => (alias.contains)(store),
+        None => store.contains::<P>()
+    }
+}
+
+// Caches a value for `P`, or for the key it is aliased to, unless one is
+// cached already.
+pub fn insert<P: Key, S: ExtensionStore + 'static>(store: &mut S, value: P::Value) -> &mut P::Value {
+    match redirect::<P, S>(store) {
+        Some(alias) => (alias.insert)(store, value),
+        None => store.entry_or_insert_with::<P, _>(|| value)
+    }
+}
+
+// Caches a value for `P`, or for the key it is aliased to, returning the
+// value it replaces.
+pub fn replace<P: Key, S: ExtensionStore + 'static>(store: &mut S, value: P::Value) -> Option<P::Value> {
+    match redirect::<P, S>(store) {
+        Some(alias) => {
+            let old = (alias.remove)(store);
+            (alias.insert)(store, value);
+            old
+        },
+        None => store.insert::<P>(value)
+    }
+}
+
+// Removes the value cached for `P`, or for the key it is aliased to.
+pub fn remove<P: Key, S: ExtensionStore + 'static>(store: &mut S) -> Option<P::Value> {
+    match redirect::<P, S>(store) {
+        Some(alias) => (alias.remove)(store),
+        None => store.remove::<P>()
+    }
+}
+
+fn contains_aliased<New: Key, S: ExtensionStore>(store: &S) -> bool {
+    store.contains::<New>()
+}
+
+fn get_aliased<New: Key, S: ExtensionStore>(store: &S) -> Option<&New::Value> {
+    store.get::<New>()
+}
+
+fn get_mut<New: Key, S: ExtensionStore>(store: &mut S) -> &mut New::Value {
+    store.get_mut::<New>().unwrap()
+}
+
+fn insert_aliased<New: Key, S: ExtensionStore>(store: &mut S, value: New::Value) -> &mut New::Value {
+    store.entry_or_insert_with::<New, _>(|| value)
+}
+
+fn remove_aliased<New: Key, S: ExtensionStore>(store: &mut S) -> Option<New::Value> {
+    store.remove::<New>()
+}
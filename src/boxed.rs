@@ -0,0 +1,46 @@
+//! Inserting type-erased plugin values.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use typemap::{TypeMap, Key};
+
+use evict;
+
+// Caches a boxed value under its key, or returns it if it has another type.
+type InsertBoxed = fn(&mut TypeMap, Box<dyn Any>) -> Result<(), Box<dyn Any>>;
+
+struct Inserters;
+
+impl Key for Inserters { type Value = HashMap<TypeId, InsertBoxed>; }
+
+pub fn register<P: Key>(map: &mut TypeMap) {
+    map.entry::<Inserters>().or_insert_with(HashMap::new)
+        .insert(TypeId::of::<P>(), insert::<P>);
+}
+
+pub fn insert_boxed(map: &mut TypeMap, id: TypeId, value: Box<dyn Any>) -> Result<(), Box<dyn Any>> {
+    match map.get::<Inserters>().and_then(|inserters| inserters.get(&id)).cloned() {
+        Some(insert) => insert(map, value),
+        None => Err(value)
+    }
+}
+
+pub fn insert_missing<I>(map: &mut TypeMap, values: I) -> usize
+where I: IntoIterator<Item = (TypeId, Box<dyn Any>)> {
+    let mut inserted = 0;
+    for (id, value) in values {
+        // Only the keys are read, which cannot break the map's invariants.
+        if unsafe { map.data() }.contains_key(&id) { continue }
+        if insert_boxed(map, id, value).is_ok() { inserted += 1 }
+    }
+    inserted
+}
+
+fn insert<P: Key>(map: &mut TypeMap, value: Box<dyn Any>) -> Result<(), Box<dyn Any>> {
+    let value = value.downcast::<P::Value>()?;
+    if let Some(mut old) = map.insert::<P>(*value) {
+        evict::notify::<P, _>(map, &mut old);
+    }
+    Ok(())
+}
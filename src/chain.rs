@@ -0,0 +1,31 @@
+//! Inheriting cached values from parent contexts.
+
+use typemap::Key;
+
+use Extensible;
+
+/// Extensible types which may inherit cached plugin values from a parent,
+/// as in request/sub-request designs.
+///
+/// `Pluggable::get_chained` looks for a plugin's value in the type's own
+/// extensions, then in its parent's, and so on up the chain.
+pub trait Chained: Extensible {
+    /// The parent whose cache is consulted after this type's own, if any.
+    fn parent(&self) -> Option<&dyn Chained>;
+}
+
+pub fn find<P: Key, C: Chained + ?Sized>(ext: &C) -> Option<&P::Value> {
+    if let Some(value) = ext.extensions().get::<P>() {
+        return Some(value);
+    }
+
+    let mut parent = ext.parent();
+    while let Some(level) = parent {
+        if let Some(value) = level.extensions().get::<P>() {
+            return Some(value);
+        }
+        parent = level.parent();
+    }
+
+    None
+}
@@ -0,0 +1,50 @@
+//! Collecting cached values through a common trait.
+
+use std::any::{type_name, TypeId};
+use std::marker::PhantomData;
+
+use typemap::{TypeMap, Key};
+
+/// Plugins whose values can be viewed as the trait object `Dyn`.
+///
+/// Because plugin values are stored type-erased, a plugin must be
+/// registered with `Pluggable::register_as` before `Pluggable::collect_as`
+/// or `Pluggable::iter_registered` can find its value.
+///
+/// Implementations spell out the `'static` bound of the trait object,
+/// e.g. `fn upcast(value: &Value) -> &(dyn Trait + 'static)`.
+pub trait TraitPlugin<Dyn: ?Sized>: Key {
+    /// View the plugin's value as `Dyn`.
+    fn upcast(value: &Self::Value) -> &Dyn;
+}
+
+pub type Upcast<Dyn> = for<'a> fn(&'a TypeMap) -> Option<&'a Dyn>;
+
+pub struct Upcasts<Dyn: ?Sized>(PhantomData<Dyn>);
+
+impl<Dyn: ?Sized + 'static> Key for Upcasts<Dyn> {
+    type Value = Vec<(TypeId, &'static str, Upcast<Dyn>)>;
+}
+
+pub fn register<P, Dyn>(map: &mut TypeMap)
+where P: TraitPlugin<Dyn>, Dyn: ?Sized + 'static {
+    let upcasts = map.entry::<Upcasts<Dyn>>().or_insert_with(Vec::new);
+
+    if !upcasts.iter().any(|&(id, _, _)| id == TypeId::of::<P>()) {
+        upcasts.push((TypeId::of::<P>(), type_name::<P>(), upcast::<P, Dyn>));
+    }
+}
+
+pub fn collect<Dyn: ?Sized + 'static>(map: &TypeMap) -> Vec<&Dyn> {
+    iter::<Dyn>(map).map(|(_, value)| value).collect()
+}
+
+pub fn iter<Dyn: ?Sized + 'static>(map: &TypeMap) -> impl Iterator<Item = (&'static str, &Dyn)> {
+    map.get::<Upcasts<Dyn>>().into_iter().flatten()
+        .filter_map(move |&(_, name, upcast)| upcast(map).map(|value| (name, value)))
+}
+
+fn upcast<P, Dyn>(map: &TypeMap) -> Option<&Dyn>
+where P: TraitPlugin<Dyn>, Dyn: ?Sized + 'static {
+    map.get::<P>().map(P::upcast)
+}
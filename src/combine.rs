@@ -0,0 +1,25 @@
+//! Combining the values of two plugins at the call site.
+
+use std::error::Error;
+use std::fmt;
+
+/// The error returned by `Pluggable::combine`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CombineError<A, B> {
+    /// The first plugin's evaluation failed.
+    First(A),
+
+    /// The second plugin's evaluation failed.
+    Second(B)
+}
+
+impl<A: fmt::Display, B: fmt::Display> fmt::Display for CombineError<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CombineError::First(ref err) => err.fmt(f),
+            CombineError::Second(ref err) => err.fmt(f)
+        }
+    }
+}
+
+impl<A: Error, B: Error> Error for CombineError<A, B> {}
@@ -0,0 +1,45 @@
+//! Comparing the cached values of opted-in plugins across snapshots.
+
+use std::any::{type_name, TypeId};
+
+use typemap::{TypeMap, Key};
+
+/// Plugins whose cached values are compared by `diff_values`.
+///
+/// Implementing this marker opts a plugin in; it must also be registered
+/// with `Pluggable::register_comparable`, since values are stored
+/// type-erased.
+pub trait ComparablePlugin: Key {}
+
+// Checks whether a plugin's cached values differ between two maps.
+type Differs = fn(&TypeMap, &TypeMap) -> bool;
+
+struct Comparable;
+
+impl Key for Comparable { type Value = Vec<(TypeId, &'static str, Differs)>; }
+
+pub fn register<P: ComparablePlugin>(map: &mut TypeMap)
+where P::Value: PartialEq {
+    let comparable = map.entry::<Comparable>().or_insert_with(Vec::new);
+
+    if !comparable.iter().any(|&(id, _, _)| id == TypeId::of::<P>()) {
+        comparable.push((TypeId::of::<P>(), type_name::<P>(), differs::<P>));
+    }
+}
+
+pub fn diff_values(before: &TypeMap, after: &TypeMap) -> Vec<&'static str> {
+    let mut seen = Vec::new();
+    let registered = after.get::<Comparable>().into_iter()
+        .chain(before.get::<Comparable>()).flatten();
+
+    registered.filter(|&&(id, _, _)| {
+        if seen.contains(&id) { return false }
+        seen.push(id);
+        true
+    }).filter(|&&(_, _, differs)| differs(before, after)).map(|&(_, name, _)| name).collect()
+}
+
+fn differs<P: Key>(before: &TypeMap, after: &TypeMap) -> bool
+where P::Value: PartialEq {
+    before.get::<P>() != after.get::<P>()
+}
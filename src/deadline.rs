@@ -0,0 +1,44 @@
+//! Deadlines and time budgets shared by the plugins evaluated for an
+//! extended type.
+
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use typemap::Key;
+
+/// The error returned by `Pluggable::get_by_deadline` and
+/// `Pluggable::get_budgeted`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeadlineError<E> {
+    /// The deadline set with `Pluggable::set_deadline` had passed, so the
+    /// plugin was not evaluated.
+    Exceeded,
+
+    /// The time budget set with `Pluggable::set_budget` was used up, either
+    /// before the plugin was evaluated or by its evaluation.
+    BudgetExhausted,
+
+    /// The plugin's evaluation failed.
+    Plugin(E)
+}
+
+impl<E: fmt::Display> fmt::Display for DeadlineError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeadlineError::Exceeded => f.write_str("deadline exceeded before plugin evaluation"),
+            DeadlineError::BudgetExhausted => f.write_str("plugin evaluation time budget exhausted"),
+            DeadlineError::Plugin(ref err) => err.fmt(f)
+        }
+    }
+}
+
+impl<E: Error> Error for DeadlineError<E> {}
+
+pub struct Deadline;
+
+impl Key for Deadline { type Value = Instant; }
+
+pub struct Budget;
+
+impl Key for Budget { type Value = Duration; }
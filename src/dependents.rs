@@ -0,0 +1,164 @@
+//! Invalidating plugins derived from other plugins.
+
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use typemap::{TypeMap, Key};
+
+use {Extensible, Plugin, Pluggable};
+use evict;
+
+/// Plugins whose values are derived from the values of other plugins.
+///
+/// Once registered with `Pluggable::register_dependent`, the plugin is
+/// invalidated by `Pluggable::recompute_dependents` whenever any of its
+/// dependencies is refreshed. Once registered with
+/// `Pluggable::register_warm_up`, it is evaluated after its dependencies by
+/// `Pluggable::warm_up_sorted`.
+pub trait DependentPlugin<E: ?Sized>: Plugin<E> {
+    /// The `TypeId`s of the plugins this plugin's value is derived from.
+    fn dependencies() -> Vec<TypeId>;
+}
+
+// Removes a plugin's cached values, returning `true` if it was cached.
+type Invalidate = fn(&mut TypeMap) -> bool;
+
+struct Dependents;
+
+impl Key for Dependents { type Value = HashMap<TypeId, Vec<(TypeId, Invalidate)>>; }
+
+pub fn register<P: DependentPlugin<E>, E: ?Sized>(map: &mut TypeMap) {
+    let registry = map.entry::<Dependents>().or_insert_with(HashMap::new);
+
+    for dependency in P::dependencies() {
+        let dependents = registry.entry(dependency).or_insert_with(Vec::new);
+        if !dependents.iter().any(|&(id, _)| id == TypeId::of::<P>()) {
+            dependents.push((TypeId::of::<P>(), invalidate::<P>));
+        }
+    }
+}
+
+pub fn invalidate_dependents(map: &mut TypeMap, root: TypeId) -> usize {
+    let mut pending = vec![root];
+    let mut seen = HashSet::new();
+    let mut invalidated = 0;
+    seen.insert(root);
+
+    while let Some(id) = pending.pop() {
+        let dependents = match map.get::<Dependents>().and_then(|registry| registry.get(&id)) {
+            Some(dependents) => dependents.clone(),
+            None => continue
+        };
+
+        for (dependent, invalidate) in dependents {
+            if seen.insert(dependent) {
+                if invalidate(map) { invalidated += 1 }
+                pending.push(dependent);
+            }
+        }
+    }
+
+    invalidated
+}
+
+pub fn invalidate<K: Key>(map: &mut TypeMap) -> bool {
+    evict::discard::<K, _>(map).is_some()
+}
+
+/// The error returned by `Pluggable::warm_up_sorted`.
+#[derive(Debug)]
+pub enum WarmUpError {
+    /// The registered plugins depend on each other in a cycle, so none
+    /// of them was evaluated.
+    Recursion,
+
+    /// A plugin's evaluation failed, with the plugin's `TypeId` and its
+    /// boxed `Plugin::Error`.
+    Plugin(TypeId, Box<dyn Any>)
+}
+
+impl fmt::Display for WarmUpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WarmUpError::Recursion => f.write_str("plugins depend on each other in a cycle"),
+            WarmUpError::Plugin(id, _) => write!(f, "plugin {:?} failed to warm up", id)
+        }
+    }
+}
+
+impl Error for WarmUpError {}
+
+// Evaluates a plugin unless it is cached, returning its error on failure.
+type WarmUp<E> = fn(&mut E) -> Result<(), Box<dyn Any>>;
+
+type Dependencies = fn() -> Vec<TypeId>;
+
+struct WarmUps<E: ?Sized>(PhantomData<E>);
+
+impl<E: ?Sized + 'static> Key for WarmUps<E> {
+    type Value = Vec<(TypeId, Dependencies, WarmUp<E>)>;
+}
+
+pub fn register_warm_up<P, E>(map: &mut TypeMap)
+where P: DependentPlugin<E>, P::Error: Any, E: Extensible + Pluggable + ?Sized + 'static {
+    let warm_ups = map.entry::<WarmUps<E>>().or_insert_with(Vec::new);
+
+    if !warm_ups.iter().any(|&(id, _, _)| id == TypeId::of::<P>()) {
+        warm_ups.push((TypeId::of::<P>(), P::dependencies, warm_up::<P, E>));
+    }
+}
+
+pub fn warm_up_sorted<E>(ext: &mut E) -> Result<Vec<TypeId>, WarmUpError>
+where E: Extensible + ?Sized + 'static {
+    let warm_ups = match ext.extensions().get::<WarmUps<E>>() {
+        Some(warm_ups) => warm_ups.clone(),
+        None => return Ok(Vec::new())
+    };
+
+    let index = warm_ups.iter().enumerate().map(|(i, &(id, _, _))| (id, i)).collect();
+    let mut visited = vec![Visit::New; warm_ups.len()];
+    let mut order = Vec::with_capacity(warm_ups.len());
+    for i in 0..warm_ups.len() {
+        visit(i, &warm_ups, &index, &mut visited, &mut order)?;
+    }
+
+    for &i in &order {
+        let (id, _, warm_up) = warm_ups[i];
+        warm_up(ext).map_err(|err| WarmUpError::Plugin(id, err))?;
+    }
+
+    Ok(order.into_iter().map(|i| warm_ups[i].0).collect())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit { New, Visiting, Done }
+
+// Appends the plugin at `i` to `order` after its registered dependencies,
+// failing if it depends on itself through them.
+fn visit<E: ?Sized>(i: usize, warm_ups: &[(TypeId, Dependencies, WarmUp<E>)],
+                    index: &HashMap<TypeId, usize>, visited: &mut [Visit],
+                    order: &mut Vec<usize>) -> Result<(), WarmUpError> {
+    match visited[i] {
+        Visit::Done => return Ok(()),
+        Visit::Visiting => return Err(WarmUpError::Recursion),
+        Visit::New => visited[i] = Visit::Visiting
+    }
+
+    for dependency in (warm_ups[i].1)() {
+        if let Some(&j) = index.get(&dependency) {
+            visit(j, warm_ups, index, visited, order)?;
+        }
+    }
+
+    visited[i] = Visit::Done;
+    order.push(i);
+    Ok(())
+}
+
+fn warm_up<P, E>(ext: &mut E) -> Result<(), Box<dyn Any>>
+where P: Plugin<E>, P::Error: Any, E: Extensible + Pluggable + ?Sized {
+    ext.get_mut::<P>().map(|_| ()).map_err(|err| Box::new(err) as Box<dyn Any>)
+}
@@ -0,0 +1,99 @@
+//! Teardown hooks for values leaving the cache.
+
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+
+use typemap::{TypeMap, Key};
+
+use {ExtensionStore, PinnedValue, RcValue};
+use {alias, lookup, version};
+
+/// Plugins which need to run teardown logic when their value leaves the cache.
+///
+/// Once registered with `Pluggable::register_evictable`, `on_evict` is
+/// called whenever the plugin's cached value is removed by `invalidate`,
+/// `take`, `clear_extensions` or `clear_with`, or replaced by `insert`.
+/// Values of unregistered plugins are simply dropped or returned as before.
+pub trait EvictablePlugin: Key {
+    /// Tear down a value which is leaving the cache.
+    ///
+    /// If the value is being returned to the caller, as with `take`, the
+    /// caller receives it after this hook has run.
+    fn on_evict(value: &mut Self::Value);
+}
+
+struct Evictor<P>(PhantomData<P>);
+
+impl<P: Key> Key for Evictor<P> { type Value = fn(&mut P::Value); }
+
+struct Evictors;
+
+impl Key for Evictors { type Value = Vec<(TypeId, fn(&mut TypeMap), fn(&mut dyn Any))>; }
+
+pub fn register<P: EvictablePlugin>(map: &mut TypeMap) {
+    map.insert::<Evictor<P>>(P::on_evict);
+
+    let evictors = map.entry::<Evictors>().or_insert_with(Vec::new);
+    if !evictors.iter().any(|&(id, _, _)| id == TypeId::of::<P>()) {
+        evictors.push((TypeId::of::<P>(), evict::<P>, notify_erased::<P>));
+    }
+}
+
+pub fn notify<P: Key, S: ExtensionStore + ?Sized>(store: &S, value: &mut P::Value) {
+    if let Some(on_evict) = store.get::<Evictor<P>>() {
+        on_evict(value)
+    }
+}
+
+// Removes everything cached for a plugin: its value, the handles and the
+// pinned copy made from it, and its generation stamp. The value is returned
+// after its eviction hook has run.
+pub fn discard<P: Key, S: ExtensionStore + 'static>(store: &mut S) -> Option<P::Value> {
+    lookup::unshare::<P, _>(store);
+    store.remove::<PinnedValue<P>>();
+    store.remove::<RcValue<P>>();
+    version::clear::<P, _>(store);
+
+    let mut value = alias::remove::<P, _>(store)?;
+    notify::<P, _>(store, &mut value);
+    Some(value)
+}
+
+pub fn evict_all(map: &mut TypeMap) {
+    if let Some(evictors) = map.get::<Evictors>().cloned() {
+        for (_, evict, _) in evictors { evict(map) }
+    }
+}
+
+pub fn evict_id(map: &mut TypeMap, id: TypeId) {
+    let evict = map.get::<Evictors>()
+        .and_then(|evictors| evictors.iter().find(|&&(evicted, _, _)| evicted == id).map(|&(_, evict, _)| evict));
+
+    if let Some(evict) = evict { evict(map) }
+}
+
+pub fn drain<F>(map: &mut TypeMap, mut f: F)
+where F: FnMut(TypeId, Box<dyn Any>) {
+    let evictors = map.get::<Evictors>().cloned().unwrap_or_default();
+
+    // Every entry is removed, which cannot break the map's invariants.
+    for (id, value) in unsafe { map.data_mut() }.drain() {
+        let mut value: Box<dyn Any> = value;
+        if let Some(&(_, _, notify)) = evictors.iter().find(|&&(evicted, _, _)| evicted == id) {
+            notify(&mut *value)
+        }
+        f(id, value)
+    }
+}
+
+fn evict<P: Key>(map: &mut TypeMap) {
+    if let Some(mut value) = map.remove::<P>() {
+        notify::<P, _>(map, &mut value);
+    }
+}
+
+fn notify_erased<P: EvictablePlugin>(value: &mut dyn Any) {
+    if let Some(value) = value.downcast_mut::<P::Value>() {
+        P::on_evict(value)
+    }
+}
@@ -0,0 +1,34 @@
+//! Call-site conveniences built on `Pluggable`.
+
+use std::any::Any;
+
+use {ExtensibleStore, Plugin, Pluggable};
+
+/// Extra accessors for every `Pluggable` type.
+///
+/// These are kept out of `Pluggable` so that the core trait stays small.
+/// Bring them into scope with `use plugin::PluginExt;` to opt in.
+pub trait PluginExt: Pluggable + ExtensibleStore {
+    /// Return a copy of the plugin's produced value, or `None` if
+    /// plugin creation fails.
+    fn get_opt<P: Plugin<Self>>(&mut self) -> Option<P::Value>
+    where P::Value: Clone + Any {
+        self.get::<P>().ok()
+    }
+
+    /// Return a copy of the plugin's produced value, converting any
+    /// error with `f`.
+    fn get_mapped<P: Plugin<Self>, F, E>(&mut self, f: F) -> Result<P::Value, E>
+    where P::Value: Clone + Any, F: FnOnce(P::Error) -> E {
+        self.get::<P>().map_err(f)
+    }
+
+    /// Return a copy of the plugin's produced value, or the value type's
+    /// default if plugin creation fails. The default is not cached.
+    fn get_or_default<P: Plugin<Self>>(&mut self) -> P::Value
+    where P::Value: Clone + Default + Any {
+        self.get::<P>().unwrap_or_default()
+    }
+}
+
+impl<T: Pluggable + ExtensibleStore + ?Sized> PluginExt for T {}
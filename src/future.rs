@@ -0,0 +1,44 @@
+//! Plugins evaluated asynchronously.
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use typemap::Key;
+
+/// Plugins whose evaluation is asynchronous.
+///
+/// The future returned by `eval` may borrow the extended type. These
+/// plugins are evaluated by `Pluggable::get_blocking`, which drives the
+/// future to completion on the calling thread.
+pub trait AsyncPlugin<E: ?Sized>: Key {
+    /// The error type of a failed evaluation.
+    type Error;
+
+    /// The future evaluating the plugin.
+    type Future<'a>: Future<Output = Result<Self::Value, Self::Error>> where E: 'a;
+
+    /// Start evaluating the plugin.
+    fn eval(ext: &mut E) -> Self::Future<'_>;
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) { self.0.unpark() }
+}
+
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park()
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Plugin values shared by every context in the process.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use Plugin;
+
+/// Plugins whose value doesn't depend on the context evaluating them, so
+/// one value can be shared process-wide.
+///
+/// Evaluated through `Pluggable::get_global`, the value is cached in a
+/// process-wide map keyed by the plugin's `TypeId`, shared across threads
+/// and across every extended type the plugin is implemented for. Values
+/// must therefore be `Send + Sync`.
+pub trait GlobalPlugin<E: ?Sized>: Plugin<E> where Self::Value: Send + Sync {}
+
+type Value = Arc<dyn Any + Send + Sync>;
+
+// A plugin's process-wide value, initialised by one thread at a time so
+// that racing threads wait for the first evaluation instead of repeating
+// it. `OnceLock` can't be initialised fallibly, so a failed evaluation
+// leaves the value unset for the next waiting thread to retry.
+#[derive(Default)]
+struct Slot {
+    value: OnceLock<Value>,
+    init: Mutex<()>
+}
+
+type Globals = Mutex<HashMap<TypeId, Arc<Slot>>>;
+
+static GLOBALS: OnceLock<Globals> = OnceLock::new();
+
+pub fn get<P, E>(ext: &mut E) -> Result<Arc<P::Value>, P::Error>
+where P: GlobalPlugin<E>, P::Value: Send + Sync, E: ?Sized {
+    // The map's lock is only held to find the plugin's slot, so the plugin
+    // may itself request other global values while it is evaluated.
+    let slot = globals().lock().unwrap_or_else(|err| err.into_inner())
+        .entry(TypeId::of::<P>()).or_default().clone();
+
+    if let Some(value) = slot.value.get() {
+        return Ok(downcast(value));
+    }
+
+    let _init = slot.init.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(value) = slot.value.get() {
+        return Ok(downcast(value));
+    }
+
+    let value: Value = Arc::new(P::eval(ext)?);
+    Ok(downcast(slot.value.get_or_init(|| value)))
+}
+
+fn downcast<V: Any + Send + Sync>(value: &Value) -> Arc<V> {
+    value.clone().downcast().unwrap()
+}
+
+fn globals() -> &'static Globals {
+    GLOBALS.get_or_init(Default::default)
+}
@@ -0,0 +1,55 @@
+//! Grouping plugins under a name.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use typemap::{TypeMap, Key};
+
+use dependents;
+
+type Get = for<'a> fn(&'a TypeMap) -> Option<&'a dyn Any>;
+
+// Removes a plugin's cached values, returning `true` if it was cached.
+type Invalidate = fn(&mut TypeMap) -> bool;
+
+struct Groups;
+
+impl Key for Groups { type Value = HashMap<&'static str, Vec<(TypeId, Get, Invalidate)>>; }
+
+pub fn register<P: Key>(map: &mut TypeMap, group: &'static str) {
+    let members = map.entry::<Groups>().or_insert_with(HashMap::new)
+        .entry(group).or_insert_with(Vec::new);
+
+    if !members.iter().any(|&(id, _, _)| id == TypeId::of::<P>()) {
+        members.push((TypeId::of::<P>(), get::<P>, dependents::invalidate::<P>));
+    }
+}
+
+pub fn iter<'a, T: Any>(map: &'a TypeMap, group: &str) -> impl Iterator<Item = &'a T> + 'a {
+    map.get::<Groups>().and_then(|groups| groups.get(group)).into_iter()
+        .flat_map(move |members| members.iter().filter_map(move |&(_, get, _)| get(map)))
+        .filter_map(|value| value.downcast_ref::<T>())
+}
+
+pub fn invalidate(map: &mut TypeMap, group: &str) -> usize {
+    let members = match map.get::<Groups>().and_then(|groups| groups.get(group)) {
+        Some(members) => members.clone(),
+        None => return 0
+    };
+
+    members.into_iter().filter(|&(_, _, invalidate)| invalidate(map)).count()
+}
+
+#[cfg(feature = "stats")]
+pub fn counts(map: &TypeMap) -> HashMap<&'static str, usize> {
+    match map.get::<Groups>() {
+        Some(groups) => groups.iter().map(|(&group, members)| {
+            (group, members.iter().filter(|&&(_, get, _)| get(map).is_some()).count())
+        }).collect(),
+        None => HashMap::new()
+    }
+}
+
+fn get<P: Key>(map: &TypeMap) -> Option<&dyn Any> {
+    map.get::<P>().map(|value| value as &dyn Any)
+}
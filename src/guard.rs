@@ -0,0 +1,98 @@
+//! Guarding plugin evaluation against recursion.
+
+use std::any::TypeId;
+use std::error::Error;
+use std::fmt;
+#[cfg(debug_assertions)]
+use std::marker::PhantomData;
+
+use typemap::Key;
+
+/// The error returned by `Pluggable::guarded_eval`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError<E> {
+    /// The plugin was already being evaluated through `guarded_eval`
+    /// further up the call stack.
+    Recursion,
+
+    /// The plugin's evaluation failed.
+    Plugin(E)
+}
+
+impl<E: fmt::Display> fmt::Display for EvalError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EvalError::Recursion => f.write_str("plugin evaluated recursively"),
+            EvalError::Plugin(ref err) => err.fmt(f)
+        }
+    }
+}
+
+impl<E: Error> Error for EvalError<E> {}
+
+/// The error returned by `Pluggable::get_depth_limited`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DepthError<E> {
+    /// Evaluating the plugin would nest evaluations through
+    /// `get_depth_limited` deeper than the limit set with
+    /// `Pluggable::set_max_depth`.
+    DepthLimitExceeded,
+
+    /// The plugin's evaluation failed.
+    Plugin(E)
+}
+
+impl<E: fmt::Display> fmt::Display for DepthError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DepthError::DepthLimitExceeded => f.write_str("plugin evaluation depth limit exceeded"),
+            DepthError::Plugin(ref err) => err.fmt(f)
+        }
+    }
+}
+
+impl<E: Error> Error for DepthError<E> {}
+
+/// The error returned by `Pluggable::get_with_fuel`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FuelError<E> {
+    /// Every evaluation allowed by the fuel was spent, so the plugin was
+    /// not evaluated.
+    FuelExhausted,
+
+    /// The plugin's evaluation failed.
+    Plugin(E)
+}
+
+impl<E: fmt::Display> fmt::Display for FuelError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FuelError::FuelExhausted => f.write_str("plugin evaluation fuel exhausted"),
+            FuelError::Plugin(ref err) => err.fmt(f)
+        }
+    }
+}
+
+impl<E: Error> Error for FuelError<E> {}
+
+pub struct Evaluating;
+
+impl Key for Evaluating { type Value = Vec<TypeId>; }
+
+pub struct Depth;
+
+impl Key for Depth { type Value = usize; }
+
+pub struct MaxDepth;
+
+impl Key for MaxDepth { type Value = usize; }
+
+pub struct Fuel;
+
+impl Key for Fuel { type Value = usize; }
+
+#[cfg(debug_assertions)]
+pub struct Evaluated<P>(PhantomData<P>);
+
+#[cfg(debug_assertions)]
+impl<P: Key> Key for Evaluated<P> { type Value = (); }
@@ -0,0 +1,52 @@
+//! Hashing the cached values of opted-in plugins.
+
+use std::any::TypeId;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use typemap::{TypeMap, Key};
+
+/// Plugins whose cached values are included in `Pluggable::extensions_hash`.
+///
+/// Implementing this marker opts a plugin in; it must also be registered
+/// with `Pluggable::register_hashable`, since values are stored type-erased.
+pub trait HashablePlugin: Key {}
+
+// Hashes a plugin's cached value, if it is cached.
+type HashValue = fn(&TypeMap) -> Option<u64>;
+
+struct Hashable;
+
+impl Key for Hashable { type Value = Vec<(TypeId, HashValue)>; }
+
+pub fn register<P: HashablePlugin>(map: &mut TypeMap)
+where P::Value: Hash {
+    let hashable = map.entry::<Hashable>().or_insert_with(Vec::new);
+
+    if !hashable.iter().any(|&(id, _)| id == TypeId::of::<P>()) {
+        hashable.push((TypeId::of::<P>(), hash_value::<P>));
+    }
+}
+
+pub fn hash(map: &TypeMap) -> u64 {
+    let mut hashes: Vec<(TypeId, u64)> = match map.get::<Hashable>() {
+        Some(hashable) => hashable.iter()
+            .filter_map(|&(id, hash_value)| hash_value(map).map(|hash| (id, hash)))
+            .collect(),
+        None => Vec::new()
+    };
+    hashes.sort();
+
+    let mut hasher = DefaultHasher::new();
+    hashes.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn hash_value<P: Key>(map: &TypeMap) -> Option<u64>
+where P::Value: Hash {
+    map.get::<P>().map(|value| {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    })
+}
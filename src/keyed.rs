@@ -0,0 +1,88 @@
+//! Plugins whose values are cached once per runtime key.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+#[cfg(feature = "parallel")]
+use std::panic;
+#[cfg(feature = "parallel")]
+use std::thread;
+
+use typemap::Key;
+
+use ExtensionStore;
+
+/// Implementers of this trait are plugins evaluated with a runtime key,
+/// via `OtherType::get_keyed_ref<P>(arg)`.
+///
+/// Unlike `ArgPlugin`, the value is cached: each key's value is kept in a
+/// `HashMap` in the extensions, so the plugin is evaluated at most once per
+/// key. `Pluggable::populate_keyed` warms the cache for a known set of keys.
+pub trait KeyedPlugin<E: ?Sized>: Key {
+    /// The key each value is cached under.
+    type Arg: Hash + Eq + 'static;
+
+    /// The error type associated with this plugin.
+    type Error;
+
+    /// Create the plugin from an instance of the extended type and a key.
+    fn eval(ext: &mut E, arg: &Self::Arg) -> Result<Self::Value, Self::Error>;
+}
+
+/// Keyed plugins whose values depend on the key alone.
+///
+/// Such plugins don't need the extended type, so
+/// `Pluggable::populate_keyed_parallel` can evaluate them across threads.
+///
+/// Requires the `parallel` feature.
+#[cfg(feature = "parallel")]
+pub trait PureKeyedPlugin<E: ?Sized>: KeyedPlugin<E> {
+    /// Create the plugin from a key, without the extended type.
+    fn eval_pure(arg: &Self::Arg) -> Result<Self::Value, Self::Error>;
+}
+
+pub struct Cache<P, A>(PhantomData<(P, A)>);
+
+impl<P: Key, A: Hash + Eq + 'static> Key for Cache<P, A> { type Value = HashMap<A, P::Value>; }
+
+pub fn get<'s, P, E, S>(store: &'s S, arg: &P::Arg) -> Option<&'s P::Value>
+where P: KeyedPlugin<E>, E: ?Sized, S: ExtensionStore + ?Sized {
+    store.get::<Cache<P, P::Arg>>()?.get(arg)
+}
+
+pub fn insert<P, E, S>(store: &mut S, arg: P::Arg, value: P::Value) -> &mut P::Value
+where P: KeyedPlugin<E>, E: ?Sized, S: ExtensionStore + ?Sized {
+    store.entry_or_insert_with::<Cache<P, P::Arg>, _>(HashMap::new).entry(arg).or_insert(value)
+}
+
+// Evaluates `args` on up to one thread per available core, returning each
+// key together with its result, in order.
+#[cfg(feature = "parallel")]
+#[allow(clippy::type_complexity)]
+pub fn eval_parallel<P, E>(args: Vec<P::Arg>) -> Vec<(P::Arg, Result<P::Value, P::Error>)>
+where P: PureKeyedPlugin<E>, E: ?Sized, P::Arg: Send, P::Value: Send, P::Error: Send {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = args.len().div_ceil(threads).max(1);
+
+    let mut chunks = Vec::new();
+    let mut args = args.into_iter();
+    loop {
+        let next: Vec<P::Arg> = args.by_ref().take(chunk).collect();
+        if next.is_empty() { break }
+        chunks.push(next);
+    }
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = chunks.into_iter().map(|chunk| scope.spawn(move || {
+            chunk.into_iter().map(|arg| {
+                let result = P::eval_pure(&arg);
+                (arg, result)
+            }).collect::<Vec<_>>()
+        })).collect();
+
+        handles.into_iter().flat_map(|handle| match handle.join() {
+            Ok(results) => results,
+            Err(payload) => panic::resume_unwind(payload)
+        }).collect()
+    })
+}
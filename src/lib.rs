@@ -4,9 +4,130 @@
 
 extern crate typemap;
 
-use std::any::Any;
+#[cfg(feature = "void")]
+extern crate void;
+
+use std::any::{Any, TypeId};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+
+#[cfg(feature = "stats")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 use typemap::{TypeMap, Key};
 
+pub use chain::Chained;
+pub use combine::CombineError;
+pub use compare::ComparablePlugin;
+pub use collect::TraitPlugin;
+pub use dependents::{DependentPlugin, WarmUpError};
+pub use evict::EvictablePlugin;
+pub use ext::PluginExt;
+pub use guard::{DepthError, EvalError, FuelError};
+pub use hash::HashablePlugin;
+pub use parent::WithParent;
+pub use read::{NotCached, PluggableRead};
+pub use send::{SendExtensible, SendPluggable};
+pub use sink::ErrorSink;
+pub use store::{ExtensionStore, ExtensibleStore};
+pub use stream::{Stream, StreamingPlugin};
+pub use sync::{SyncExtensible, SyncPluggable};
+pub use trace::TraceId;
+pub use version::GenerationSensitive;
+
+#[cfg(feature = "std")]
+pub use deadline::DeadlineError;
+
+#[cfg(feature = "std")]
+pub use global::GlobalPlugin;
+
+#[cfg(feature = "std")]
+pub use ttl::TtlPlugin;
+
+pub use keyed::KeyedPlugin;
+
+#[cfg(feature = "parallel")]
+pub use keyed::PureKeyedPlugin;
+
+#[cfg(feature = "poison")]
+pub use poison::PoisonError;
+
+#[cfg(feature = "thread")]
+pub use prefetch::PrefetchPlugin;
+
+#[cfg(feature = "thread")]
+pub use scoped::PluginRef;
+
+#[cfg(feature = "async")]
+pub use future::AsyncPlugin;
+
+#[macro_use]
+#[doc(hidden)]
+pub mod macros;
+
+mod alias;
+mod boxed;
+mod chain;
+mod combine;
+mod collect;
+mod compare;
+mod dependents;
+mod evict;
+mod ext;
+mod group;
+mod guard;
+mod hash;
+mod lookup;
+mod named;
+mod parent;
+mod read;
+mod recompute;
+mod report;
+mod send;
+mod sink;
+mod store;
+mod stream;
+mod sync;
+mod trace;
+mod version;
+
+#[cfg(feature = "test-util")]
+#[doc(hidden)]
+#[macro_use]
+pub mod test_util;
+
+#[cfg(feature = "std")]
+mod deadline;
+
+#[cfg(feature = "std")]
+mod global;
+
+#[cfg(feature = "std")]
+mod observe;
+
+#[cfg(feature = "std")]
+mod ttl;
+
+mod keyed;
+
+#[cfg(feature = "poison")]
+mod poison;
+
+#[cfg(feature = "thread")]
+mod prefetch;
+
+#[cfg(feature = "thread")]
+mod scoped;
+
+#[cfg(feature = "async")]
+mod future;
+
 /// Implementers of this trait can act as plugins for other types, via `OtherType::get<P>()`.
 ///
 /// To create a plugin, implement this trait and provide an empty implementation
@@ -21,18 +142,96 @@ pub trait Plugin<E: ?Sized>: Key {
     /// type, it is important for implementers to remember that
     /// the result of `eval` is usually cached, so care should
     /// be taken when doing mutation on the extended type.
-    fn eval(&mut E) -> Result<Self::Value, Self::Error>;
+    fn eval(ext: &mut E) -> Result<Self::Value, Self::Error>;
+}
+
+/// Implementers of this trait produce views borrowing from other types,
+/// via `OtherType::view<P>()`.
+///
+/// Unlike `Plugin`, whose values are owned and cached in the extended type's
+/// `TypeMap`, a view borrows from the extended type itself. It therefore
+/// cannot be stored alongside the data it borrows, and is computed afresh
+/// on every call. Use it for cheap, zero-copy derived data such as string
+/// slices or structs of references.
+pub trait ViewPlugin<E: ?Sized> {
+    /// The view produced, borrowing from the extended type for `'a`.
+    type View<'a> where E: 'a;
+
+    /// Create the view from an instance of the extended type.
+    fn view(ext: &E) -> Self::View<'_>;
+}
+
+/// Implementers of this trait are plugins evaluated with a runtime argument,
+/// via `OtherType::compute_with_arg<P>(arg)`.
+///
+/// Because the value depends on the argument, it is never cached: every
+/// call evaluates the plugin afresh. This covers running a plugin once with
+/// a given input, without first stashing the input on the extended type.
+pub trait ArgPlugin<E: ?Sized>: Key {
+    /// The argument passed to each evaluation.
+    type Arg;
+
+    /// The error type associated with this plugin.
+    type Error;
+
+    /// Create the plugin from an instance of the extended type and an argument.
+    fn eval(ext: &mut E, arg: Self::Arg) -> Result<Self::Value, Self::Error>;
+}
+
+/// Implementers of this trait are plugins whose evaluation also produces
+/// auxiliary output, via `OtherType::get_with_aux<P>()`.
+///
+/// Only the value is cached. The auxiliary output, such as diagnostics or
+/// warnings, is handed to the caller whose call evaluated the plugin, and
+/// then dropped.
+pub trait DiagnosticPlugin<E: ?Sized>: Key {
+    /// The auxiliary output of an evaluation.
+    type Aux;
+
+    /// The error type associated with this plugin.
+    type Error;
+
+    /// Create the plugin's value and auxiliary output from an instance of
+    /// the extended type.
+    fn eval(ext: &mut E) -> Result<(Self::Value, Self::Aux), Self::Error>;
 }
 
+/// Implementers of this trait are plugins whose values must not move once
+/// cached, via `OtherType::get_pinned<P>()`.
+///
+/// `Plugin::eval` returns the value by value, so it may move until it is
+/// cached; the value is then boxed and pinned, and stays at that address
+/// until it is invalidated. Self-referential values must therefore set up
+/// their internal pointers through `Pluggable::get_pinned_mut`, not in
+/// `eval`. The pinned value is cached separately from the value returned
+/// by `get`, which can be moved, cloned or taken, and is never pinned.
+pub trait PinPlugin<E: ?Sized>: Plugin<E> {}
+
 /// Defines an interface that extensible types must implement.
 ///
-/// Extensible types must contain a TypeMap.
+/// Extensible types must contain a TypeMap. To use other storage,
+/// implement `ExtensibleStore` instead.
+///
+/// Implementers which know roughly how many plugins will be evaluated
+/// can create their map with `new_extensions_with_capacity` to avoid
+/// rehashing during the first burst of evaluations.
 pub trait Extensible {
     /// Get a reference to the type's extension storage.
     fn extensions(&self) -> &TypeMap;
 
     /// Get a mutable reference to the type's extension storage.
     fn extensions_mut(&mut self) -> &mut TypeMap;
+
+    /// Get a reference to a parent's extension storage, whose cached values
+    /// are returned before a plugin is evaluated against this type.
+    ///
+    /// There is no parent by default. See `WithParent`.
+    fn parent_extensions(&self) -> Option<&TypeMap> { None }
+
+    /// Whether the methods which return copies of cached values, such as
+    /// `get`, also clone values found in `parent_extensions` into this
+    /// type's own extensions.
+    fn clones_from_parent(&self) -> bool { false }
 }
 
 /// An interface for plugins that cache values between calls.
@@ -44,8 +243,144 @@ pub trait Pluggable {
     ///
     /// `P` is the plugin type.
     fn get<P: Plugin<Self>>(&mut self) -> Result<P::Value, P::Error>
-    where P::Value: Clone + Any, Self: Extensible {
-        self.get_ref::<P>().map(|v| v.clone())
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        self.get_ref::<P>().cloned()
+    }
+
+    /// Return a copy of the plugin's produced value, along with how long
+    /// evaluating the plugin took.
+    ///
+    /// The duration is `Some` only if the plugin was evaluated by this call,
+    /// and `None` if its value was already cached.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "std")]
+    fn get_timed<P: Plugin<Self>>(&mut self) -> Result<(P::Value, Option<Duration>), P::Error>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok((value, None));
+        }
+
+        let start = Instant::now();
+        let value = self.get::<P>()?;
+        Ok((value, Some(start.elapsed())))
+    }
+
+    /// Return a copy of the plugin's produced value, first invalidating the
+    /// cached value if `valid` rejects it.
+    ///
+    /// A rejected value is removed as with `invalidate` and the plugin is
+    /// evaluated again; if that evaluation fails, nothing stays cached. On
+    /// a cache miss the plugin is evaluated as with `get`, and the fresh
+    /// value is not checked.
+    ///
+    /// `P` is the plugin type.
+    fn get_validated<P: Plugin<Self>, F>(&mut self, valid: F) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, F: Fn(&P::Value) -> bool, Self: ExtensibleStore {
+        if lookup::cached::<P, Self>(self).is_some_and(|value| !valid(value)) {
+            self.invalidate::<P>();
+        }
+
+        self.get::<P>()
+    }
+
+    /// Return a copy of the plugin's produced value, caching a newly
+    /// evaluated value only if `should_cache` approves of it.
+    ///
+    /// An already cached value is returned without consulting
+    /// `should_cache`. Rejected values are returned but not cached, so the
+    /// plugin is evaluated again on the next call.
+    ///
+    /// `P` is the plugin type.
+    fn get_cache_if<P: Plugin<Self>, F>(&mut self, should_cache: F) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, F: FnOnce(&P::Value) -> bool, Self: ExtensibleStore {
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        match lookup::eval::<P, Self>(self) {
+            Ok(value) => {
+                if should_cache(&value) { lookup::store::<P, Self>(self, value.clone()); }
+                Ok(value)
+            },
+            Err(err) => self.store().get::<Fallback<P>>().cloned().ok_or(err)
+        }
+    }
+
+    /// Clone the plugin's produced value into `out`.
+    ///
+    /// The plugin will be created if it doesn't exist already. The value is
+    /// copied with `Clone::clone_from`, which reuses `out`'s allocations
+    /// where the value type allows, so a buffer reused across calls avoids
+    /// the allocations of `get`. If plugin creation fails, `out` is left
+    /// untouched.
+    ///
+    /// `P` is the plugin type.
+    fn clone_into_buffer<P: Plugin<Self>>(&mut self, out: &mut P::Value) -> Result<(), P::Error>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        out.clone_from(self.get_ref::<P>()?);
+        Ok(())
+    }
+
+    /// Apply `f` to the plugin's produced value and return the result.
+    ///
+    /// The plugin will be created if it doesn't exist already, then `f`
+    /// runs on a reference to the cached value. This extracts a small part
+    /// of a large value without cloning all of it, as `get` would.
+    ///
+    /// `P` is the plugin type.
+    fn project<P: Plugin<Self>, R, F>(&mut self, f: F) -> Result<R, P::Error>
+    where P::Value: Any, F: FnOnce(&P::Value) -> R, Self: ExtensibleStore {
+        self.get_ref::<P>().map(f)
+    }
+
+    /// Return a handle which evaluates and caches the plugin when called.
+    ///
+    /// Calling the handle is equivalent to calling `get::<P>()` at that
+    /// point. This is an associated function, called as
+    /// `Type::lazy::<P>()`, so the handle borrows no instance of the type:
+    /// it can be stored, for instance in a queue, while instances are used
+    /// and borrowed mutably in the meantime. In return, an instance must be
+    /// passed back in when the handle is called, and the value is cached in
+    /// that instance. Nothing is evaluated until then.
+    ///
+    /// `P` is the plugin type.
+    fn lazy<P: Plugin<Self>>() -> impl FnOnce(&mut Self) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        Self::get::<P>
+    }
+
+    /// Return a copy of the plugin's produced value, calling `warn` with
+    /// the plugin's type name and the elapsed time if evaluating it took
+    /// longer than `threshold`.
+    ///
+    /// Nothing is timed or reported on a cache hit.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "std")]
+    fn get_warn_slow<P: Plugin<Self>, F>(&mut self, threshold: Duration, warn: F) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, F: FnOnce(&'static str, Duration), Self: ExtensibleStore {
+        let (value, elapsed) = self.get_timed::<P>()?;
+        if let Some(elapsed) = elapsed.filter(|&elapsed| elapsed > threshold) {
+            warn(std::any::type_name::<P>(), elapsed);
+        }
+        Ok(value)
+    }
+
+    /// Return a copy of the plugin's produced value, pairing any error
+    /// with the plugin's type name.
+    ///
+    /// The name comes from `std::any::type_name` and is meant for
+    /// diagnostics. It is only looked up when evaluation fails.
+    ///
+    /// `P` is the plugin type.
+    fn get_named_error<P: Plugin<Self>>(&mut self) -> Result<P::Value, (&'static str, P::Error)>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        self.get::<P>().map_err(|err| (std::any::type_name::<P>(), err))
     }
 
     /// Return a reference to the plugin's produced value.
@@ -55,132 +390,4091 @@ pub trait Pluggable {
     ///
     /// `P` is the plugin type.
     fn get_ref<P: Plugin<Self>>(&mut self) -> Result<&P::Value, P::Error>
-    where P::Value: Any, Self: Extensible {
-        self.get_mut::<P>().map(|mutref| &*mutref)
+    where P::Value: Any, Self: ExtensibleStore {
+        if lookup::cached::<P, Self>(self).is_none() {
+            return self.get_mut::<P>().map(|mutref| &*mutref);
+        }
+
+        Ok(lookup::find::<P, Self>(self).unwrap())
     }
 
-    /// Return a mutable reference to the plugin's produced value.
+    /// Return a reference to the plugin's produced value, or to `default`
+    /// if plugin creation fails.
     ///
-    /// The plugin will be created if it doesn't exist already.
-    /// If plugin creation fail an error is returned.
+    /// The plugin will be created if it doesn't exist already. Unlike a
+    /// registered fallback, `default` is only borrowed and never cached.
     ///
     /// `P` is the plugin type.
-    fn get_mut<P: Plugin<Self>>(&mut self) -> Result<&mut P::Value, P::Error>
-    where P::Value: Any, Self: Extensible {
-        use typemap::Entry::{Occupied, Vacant};
+    fn get_ref_or<'a, P: Plugin<Self>>(&'a mut self, default: &'a P::Value) -> &'a P::Value
+    where P::Value: Any, Self: ExtensibleStore {
+        self.get_ref::<P>().unwrap_or(default)
+    }
+
+    /// Return a reference to the plugin's produced value if it satisfies
+    /// `pred`, and `None` otherwise.
+    ///
+    /// The plugin will be created if it doesn't exist already. The value
+    /// stays cached whether or not `pred` holds.
+    ///
+    /// `P` is the plugin type.
+    fn get_ref_if<P: Plugin<Self>, F>(&mut self, pred: F) -> Result<Option<&P::Value>, P::Error>
+    where P::Value: Any, F: Fn(&P::Value) -> bool, Self: ExtensibleStore {
+        self.get_ref::<P>().map(|value| Some(value).filter(|value| pred(value)))
+    }
 
-        if self.extensions().contains::<P>() {
-            return Ok(self.extensions_mut().get_mut::<P>().unwrap());
+    /// Return a reference to the plugin's produced value, calling `on_miss`
+    /// with the plugin's type name just before it is evaluated.
+    ///
+    /// `on_miss` is not called if the plugin is already cached.
+    ///
+    /// `P` is the plugin type.
+    fn get_ref_counting_miss<P: Plugin<Self>, F>(&mut self, on_miss: F) -> Result<&P::Value, P::Error>
+    where P::Value: Any, F: FnOnce(&'static str), Self: ExtensibleStore {
+        if lookup::cached::<P, Self>(self).is_none() {
+            on_miss(std::any::type_name::<P>());
         }
 
-        P::eval(self).map(move |data| {
-            match self.extensions_mut().entry::<P>() {
-                Vacant(entry) => entry.insert(data),
-                Occupied(..) => panic!("Unreachable.")
-            }
-        })
+        self.get_ref::<P>()
     }
 
-    /// Create and evaluate a once-off instance of a plugin.
-    fn compute<P: Plugin<Self>>(&mut self) -> Result<P::Value, P::Error> {
-        <P as Plugin<Self>>::eval(self)
+    /// Apply `f` to the produced values of two plugins, evaluating either of
+    /// them if needed, and return its result.
+    ///
+    /// Unlike a plugin derived from `A` and `B`, the result is not cached.
+    /// If `A` fails, `B` is not evaluated.
+    ///
+    /// `A` and `B` are the plugin types.
+    fn combine<A: Plugin<Self>, B: Plugin<Self>, R, F>(&mut self, f: F) -> Result<R, CombineError<A::Error, B::Error>>
+    where A::Value: Any, B::Value: Any, F: FnOnce(&A::Value, &B::Value) -> R, Self: ExtensibleStore {
+        self.get_mut::<A>().map_err(CombineError::First)?;
+        self.get_mut::<B>().map_err(CombineError::Second)?;
+
+        Ok(f(macros::peek_evaluated::<A, _>(self), macros::peek_evaluated::<B, _>(self)))
     }
-}
 
-#[cfg(test)]
-mod test {
-    extern crate void;
+    /// Set the correlation id of the work this context belongs to, returning
+    /// the previous id, if any.
+    ///
+    /// The id is stored in the extensions under the reserved `TraceId` key,
+    /// so plugins can read it with `current_trace_id` inside `eval`, e.g.
+    /// for logging, without it being threaded through their signatures.
+    fn set_trace_id<S: Into<String>>(&mut self, id: S) -> Option<String>
+    where Self: ExtensibleStore {
+        self.store_mut().insert::<TraceId>(id.into())
+    }
 
-    use test::void::{Void, ResultVoidExt};
+    /// Return the correlation id set with `set_trace_id`, if any.
+    fn current_trace_id(&self) -> Option<&str>
+    where Self: ExtensibleStore {
+        self.store().get::<TraceId>().map(|id| &**id)
+    }
 
-    use typemap::{TypeMap, Key};
-    use super::{Extensible, Plugin, Pluggable};
+    /// Return the current generation, which is 0 until it is first advanced
+    /// with `advance_generation`.
+    fn generation(&self) -> u64
+    where Self: ExtensibleStore {
+        self.store().get::<version::Generation>().cloned().unwrap_or(0)
+    }
 
-    struct Extended {
-        map: TypeMap
+    /// Advance the generation, returning the new generation.
+    ///
+    /// Values computed from then on are stamped with the new generation,
+    /// which `get_ref_versioned` returns alongside them.
+    fn advance_generation(&mut self) -> u64
+    where Self: ExtensibleStore {
+        let generation = self.store_mut().entry_or_insert_with::<version::Generation, _>(|| 0);
+        *generation += 1;
+        *generation
     }
 
-    impl Extended {
-        fn new() -> Extended {
-            Extended { map: TypeMap::new() }
-        }
+    /// Register `P` so that its cached value is recomputed by `get`, and
+    /// the methods built on it, once the generation has advanced past the
+    /// one it was computed at.
+    ///
+    /// Values cached directly, such as with `insert`, count as computed at
+    /// generation 0.
+    fn register_generation_sensitive<P: GenerationSensitive>(&mut self)
+    where Self: ExtensibleStore {
+        version::register::<P, _>(self.store_mut())
     }
 
-    impl Extensible for Extended {
-        fn extensions(&self) -> &TypeMap { &self.map }
-        fn extensions_mut(&mut self) -> &mut TypeMap { &mut self.map }
+    /// Return a reference to the plugin's produced value, together with the
+    /// generation it was computed at.
+    ///
+    /// Compare the version against `generation` to detect values computed
+    /// before the generation was last advanced. Values computed before the
+    /// generation was first advanced, and fallback values, are reported at
+    /// generation 0.
+    ///
+    /// `P` is the plugin type.
+    fn get_ref_versioned<P: Plugin<Self>>(&mut self) -> Result<(&P::Value, u64), P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        self.get_mut::<P>()?;
+
+        let store = self.store();
+        match alias::get::<P, _>(store) {
+            Some(value) => Ok((value, store.get::<version::Stamp<P>>().cloned().unwrap_or(0))),
+            None => Ok((store.get::<Fallback<P>>().unwrap(), 0))
+        }
     }
 
-    impl Pluggable for Extended {}
+    /// Return a reference to the produced value of a plugin which cannot fail.
+    ///
+    /// The plugin will be created if it doesn't exist already.
+    ///
+    /// `P` is the plugin type.
+    fn get_ref_infallible<P: Plugin<Self, Error = Infallible>>(&mut self) -> &P::Value
+    where P::Value: Any, Self: ExtensibleStore {
+        match self.get_ref::<P>() {
+            Ok(value) => value,
+            Err(never) => match never {}
+        }
+    }
 
-    macro_rules! generate_simple_plugin (
-        ($t:ty, $v:ident, $v2:expr) => {
-            #[derive(PartialEq, Debug, Clone)]
-            struct $v(i32);
+    /// Return a reference to the produced value of a plugin whose error is `Void`.
+    ///
+    /// Equivalent to `get_ref_infallible`, for plugins which have not yet
+    /// moved from `void::Void` to `std::convert::Infallible`.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "void")]
+    fn get_ref_void<P: Plugin<Self, Error = void::Void>>(&mut self) -> &P::Value
+    where P::Value: Any, Self: ExtensibleStore {
+        match self.get_ref::<P>() {
+            Ok(value) => value,
+            Err(never) => void::unreachable(never)
+        }
+    }
 
-            impl Key for $t { type Value = $t; }
+    /// Return a mutable reference to the plugin's produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already.
+    /// If plugin creation fail an error is returned.
+    ///
+    /// With the `poison` feature, a plugin whose evaluation panics is
+    /// poisoned, and like a poisoned `Mutex`, this method then panics
+    /// rather than evaluate it again, until `clear_poison` is called.
+    ///
+    /// `P` is the plugin type.
+    fn get_mut<P: Plugin<Self>>(&mut self) -> Result<&mut P::Value, P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        lookup::refresh::<P, Self>(self);
+        lookup::unshare::<P, _>(self.store_mut());
 
-            impl Plugin<Extended> for $t {
-                type Error = Void;
+        match alias::redirect::<P, _>(self.store()) {
+            Some(alias) if (alias.contains)(self.store()) => return Ok((alias.get_mut)(self.store_mut())),
+            None if self.store().contains::<P>() => return Ok(self.store_mut().get_mut::<P>().unwrap()),
+            _ => ()
+        }
 
-                fn eval(_: &mut Extended) -> Result<$t, Void> {
-                    Ok($v($v2))
-                }
+        #[cfg(feature = "thread")]
+        {
+            if let Some(data) = prefetch::join::<P, _>(self.store_mut()) {
+                return Ok(self.store_mut().entry_or_insert_with::<P, _>(|| data));
             }
         }
-    );
 
-    generate_simple_plugin!(One, One, 1);
-    generate_simple_plugin!(Two, Two, 2);
-    generate_simple_plugin!(Three, Three, 3);
-    generate_simple_plugin!(Four, Four, 4);
-    generate_simple_plugin!(Five, Five, 5);
-    generate_simple_plugin!(Six, Six, 6);
-    generate_simple_plugin!(Seven, Seven, 7);
-    generate_simple_plugin!(Eight, Eight, 8);
-    generate_simple_plugin!(Nine, Nine, 9);
-    generate_simple_plugin!(Ten, Ten, 10);
+        #[cfg(feature = "poison")]
+        poison::check::<P, Self>(self);
 
-    #[test] fn test_simple() {
-        let mut extended = Extended::new();
-        assert_eq!(extended.get::<One>(),   Ok(One(1)));
-        assert_eq!(extended.get::<Two>(),   Ok(Two(2)));
-        assert_eq!(extended.get::<Three>(), Ok(Three(3)));
+        #[cfg(feature = "poison")]
+        let result = poison::eval::<P, Self, _, _>(self, lookup::eval::<P, Self>);
+        #[cfg(not(feature = "poison"))]
+        let result = lookup::eval::<P, Self>(self);
+
+        match result {
+            Ok(data) => Ok(lookup::store::<P, Self>(self, data)),
+            Err(err) => self.store_mut().get_mut::<Fallback<P>>().ok_or(err)
+        }
     }
 
-    #[test] fn test_resize() {
-        let mut extended = Extended::new();
-        extended.get::<One>().void_unwrap();
-        extended.get::<Two>().void_unwrap();
-        extended.get::<Three>().void_unwrap();
-        extended.get::<Four>().void_unwrap();
-        extended.get::<Five>().void_unwrap();
-        extended.get::<Six>().void_unwrap();
-        extended.get::<Seven>().void_unwrap();
-        extended.get::<Eight>().void_unwrap();
-        extended.get::<Nine>().void_unwrap();
-        extended.get::<Ten>().void_unwrap();
-        assert_eq!(extended.get_ref::<One>(), Ok(&One(1)))
+    /// Install an observer called after every evaluation of a plugin by
+    /// the methods which cache its value, such as `get`, `get_ref` and
+    /// `get_shared`, with the plugin's `TypeId` and how long evaluating it
+    /// took.
+    ///
+    /// The observer is not called on cache hits, and time is only measured
+    /// while an observer is installed. Replaces any previous observer.
+    #[cfg(feature = "std")]
+    fn set_observer(&mut self, observer: Box<dyn Fn(TypeId, Duration)>)
+    where Self: ExtensibleStore {
+        self.store_mut().insert::<observe::Observer>(observer);
     }
 
-    #[test] fn test_custom_return_type() {
-        let mut extended = Extended::new();
+    /// Set the time budget spent by `get_budgeted`, returning the remaining
+    /// previous budget, if any.
+    #[cfg(feature = "std")]
+    fn set_budget(&mut self, budget: Duration) -> Option<Duration>
+    where Self: ExtensibleStore {
+        self.store_mut().insert::<deadline::Budget>(budget)
+    }
 
-        // Define a struct.
-        struct IntPlugin;
+    /// Return the time remaining in the budget set with `set_budget`, if any.
+    #[cfg(feature = "std")]
+    fn budget(&self) -> Option<Duration>
+    where Self: ExtensibleStore {
+        self.store().get::<deadline::Budget>().cloned()
+    }
 
-        // Map it onto an `i32` value.
-        impl Key for IntPlugin { type Value = i32; }
+    /// Return a copy of the plugin's produced value, spending the time its
+    /// evaluation takes from the budget set with `set_budget`.
+    ///
+    /// If the budget is already used up, the plugin is not evaluated and
+    /// `DeadlineError::BudgetExhausted` is returned. If the evaluation
+    /// itself uses up the budget, the value is still cached, since the work
+    /// was done, but `BudgetExhausted` is returned. Cached values are
+    /// returned without spending anything. Without a budget, this behaves
+    /// like `get`.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "std")]
+    fn get_budgeted<P: Plugin<Self>>(&mut self) -> Result<P::Value, DeadlineError<P::Error>>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
 
-        // Define the plugin evaluation function.
-        impl Plugin<Extended> for IntPlugin {
-            type Error = Void;
+        let budget = match self.budget() {
+            Some(budget) if budget == Duration::from_secs(0) => return Err(DeadlineError::BudgetExhausted),
+            Some(budget) => budget,
+            None => return self.get::<P>().map_err(DeadlineError::Plugin)
+        };
 
-            fn eval(_: &mut Extended) -> Result<i32, Void> {
-                Ok(0i32)
-            }
+        let start = Instant::now();
+        let result = self.get::<P>();
+        let remaining = budget.checked_sub(start.elapsed());
+        self.set_budget(remaining.unwrap_or_default());
+
+        match (result, remaining) {
+            (Err(err), _) => Err(DeadlineError::Plugin(err)),
+            (Ok(_), None) => Err(DeadlineError::BudgetExhausted),
+            (Ok(value), Some(_)) => Ok(value)
         }
-        assert_eq!(extended.get::<IntPlugin>().void_unwrap(), 0i32);
     }
-}
 
+    /// Set the deadline observed by `get_by_deadline`, returning the
+    /// previous deadline, if any.
+    #[cfg(feature = "std")]
+    fn set_deadline(&mut self, deadline: Instant) -> Option<Instant>
+    where Self: ExtensibleStore {
+        self.store_mut().insert::<deadline::Deadline>(deadline)
+    }
+
+    /// Return the deadline set with `set_deadline`, if any.
+    ///
+    /// Long-running plugins can check it during evaluation to give up early.
+    #[cfg(feature = "std")]
+    fn deadline(&self) -> Option<Instant>
+    where Self: ExtensibleStore {
+        self.store().get::<deadline::Deadline>().cloned()
+    }
+
+    /// Return a copy of the plugin's produced value, unless it needs to be
+    /// evaluated and the deadline set with `set_deadline` has passed.
+    ///
+    /// A cached value is returned even after the deadline. Without a
+    /// deadline, this behaves like `get`.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "std")]
+    fn get_by_deadline<P: Plugin<Self>>(&mut self) -> Result<P::Value, DeadlineError<P::Error>>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        match self.deadline() {
+            Some(deadline) if Instant::now() >= deadline => Err(DeadlineError::Exceeded),
+            _ => self.get::<P>().map_err(DeadlineError::Plugin)
+        }
+    }
+
+    /// Return a copy of the plugin's produced value, evaluating the plugin
+    /// again if its cached value has expired.
+    ///
+    /// Values evaluated by this method are cached together with the time of
+    /// evaluation and expire once `P::ttl()` has passed. An expired value is
+    /// invalidated before the plugin is evaluated again. Values cached by
+    /// other means carry no evaluation time and never expire.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "std")]
+    fn get_ttl<P: TtlPlugin<Self>>(&mut self) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, Self: Extensible {
+        let now = Instant::now();
+        if ttl::is_expired::<P, Self>(self.extensions(), now) {
+            self.extensions_mut().remove::<ttl::CachedAt<P>>();
+            self.invalidate::<P>();
+        }
+
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        let value = self.get::<P>()?;
+        if alias::contains::<P, _>(self.extensions()) {
+            ttl::register::<P, Self>(self.extensions_mut());
+            self.extensions_mut().insert::<ttl::CachedAt<P>>(now);
+        }
+        Ok(value)
+    }
+
+    /// Return a copy of the plugin's produced value, evaluating the plugin
+    /// again once the cached value is older than `ttl`, but serving the
+    /// expired value if that evaluation fails.
+    ///
+    /// The expired value stays cached while the plugin is evaluated again,
+    /// and is replaced only on success. After a failure it keeps its
+    /// original evaluation time, so the next call retries. An error is
+    /// returned only when there is no value to serve. As with `get_ttl`,
+    /// values cached by other means never expire; unlike it, they are not
+    /// removed by `sweep_expired`.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "std")]
+    fn get_ttl_graceful<P: Plugin<Self>>(&mut self, ttl: Duration) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, Self: Extensible {
+        let now = Instant::now();
+        if ttl::is_older_than::<P>(self.extensions(), ttl, now) {
+            return match lookup::eval::<P, Self>(self) {
+                Ok(value) => {
+                    self.invalidate::<P>();
+                    self.insert::<P>(value.clone());
+                    version::stamp::<P, _>(self.extensions_mut());
+                    self.extensions_mut().insert::<ttl::CachedAt<P>>(now);
+                    Ok(value)
+                },
+                Err(err) => lookup::find::<P, Self>(self).cloned().ok_or(err)
+            };
+        }
+
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        let value = self.get::<P>()?;
+        if alias::contains::<P, _>(self.extensions()) {
+            self.extensions_mut().insert::<ttl::CachedAt<P>>(now);
+        }
+        Ok(value)
+    }
+
+    /// Return a shared handle to the plugin's process-wide value,
+    /// evaluating the plugin if no context has done so yet.
+    ///
+    /// The value is cached outside this type's extensions, so every context
+    /// in the process, on any thread, shares it. A failed evaluation caches
+    /// nothing and is retried on the next call. If several threads request
+    /// the plugin at once, one evaluates it while the others wait for its
+    /// value, and retry in turn if it fails. The plugin may request other
+    /// global values while it is evaluated, but not its own.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "std")]
+    fn get_global<P: GlobalPlugin<Self>>(&mut self) -> Result<Arc<P::Value>, P::Error>
+    where P::Value: Send + Sync {
+        global::get::<P, Self>(self)
+    }
+
+    /// Invalidate every plugin evaluated through `get_ttl` whose value has
+    /// expired by `now`.
+    ///
+    /// Unexpired values and plugins never evaluated through `get_ttl` are
+    /// left untouched. Returns the number of values removed.
+    #[cfg(feature = "std")]
+    fn sweep_expired(&mut self, now: Instant) -> usize
+    where Self: Extensible {
+        ttl::sweep_expired(self.extensions_mut(), now)
+    }
+
+    /// Return a reference to the plugin's produced value, poisoning the
+    /// plugin if its evaluation panics.
+    ///
+    /// The plugin will be created if it doesn't exist already. If its
+    /// evaluation panics, the panic is caught and the plugin is marked as
+    /// poisoned: this and every later call returns `PoisonError::Poisoned`
+    /// without evaluating the plugin again, until `clear_poison` is called.
+    ///
+    /// `get_mut`, and the methods built on it such as `get` and `get_ref`,
+    /// poison the plugin in the same way, but panic on a poisoned plugin
+    /// where this method returns an error.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "poison")]
+    fn get_ref_or_poison<P: Plugin<Self>>(&mut self) -> Result<&P::Value, PoisonError<P::Error>>
+    where P::Value: Any, Self: ExtensibleStore {
+        use std::panic::{self, AssertUnwindSafe};
+
+        if self.store().contains::<poison::Poison<P>>() {
+            return Err(PoisonError::Poisoned);
+        }
+
+        if lookup::cached::<P, Self>(self).is_some() {
+            return Ok(lookup::find::<P, Self>(self).unwrap());
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(|| lookup::eval::<P, Self>(self))) {
+            Ok(Ok(data)) => Ok(lookup::store::<P, Self>(self, data)),
+            Ok(Err(err)) => match self.store().get::<Fallback<P>>() {
+                Some(fallback) => Ok(fallback),
+                None => Err(PoisonError::Plugin(err))
+            },
+            Err(_) => {
+                self.store_mut().insert::<poison::Poison<P>>(());
+                Err(PoisonError::Poisoned)
+            }
+        }
+    }
+
+    /// Clear the poison left on `P` by a panicking evaluation.
+    ///
+    /// Returns `true` if the plugin was poisoned.
+    #[cfg(feature = "poison")]
+    fn clear_poison<P: Key>(&mut self) -> bool
+    where Self: ExtensibleStore {
+        self.store_mut().remove::<poison::Poison<P>>().is_some()
+    }
+
+    /// Return a reference to the cached outcome of evaluating the plugin,
+    /// whether it succeeded or failed.
+    ///
+    /// The plugin is evaluated on the first call only; its `Result` is
+    /// cached, so failures are cached as well. The outcome is stored
+    /// separately from the value returned by `get`, and is only removed
+    /// by `clear_result`.
+    ///
+    /// `P` is the plugin type.
+    fn get_result<P: Plugin<Self>>(&mut self) -> &Result<P::Value, P::Error>
+    where P::Value: Any, P::Error: Any, Self: ExtensibleStore {
+        if !self.store().contains::<Outcome<P, P::Error>>() {
+            let outcome = lookup::eval::<P, Self>(self);
+            self.store_mut().insert::<Outcome<P, P::Error>>(outcome);
+        }
+
+        self.store().get::<Outcome<P, P::Error>>().unwrap()
+    }
+
+    /// Remove the outcome cached by `get_result`, returning it.
+    fn clear_result<P: Plugin<Self>>(&mut self) -> Option<Result<P::Value, P::Error>>
+    where P::Value: Any, P::Error: Any, Self: ExtensibleStore {
+        self.store_mut().remove::<Outcome<P, P::Error>>()
+    }
+
+    /// Return a reference to the plugin's value, looking for it first in
+    /// this type's extensions and then in each of its parents in turn.
+    ///
+    /// A value found anywhere in the chain is returned from where it is
+    /// cached. If no level has it, the plugin is evaluated against this
+    /// type and cached here, the bottom of the chain; to cache a value
+    /// for every child, evaluate it on the parent instead.
+    ///
+    /// `P` is the plugin type.
+    fn get_chained<P: Plugin<Self>>(&mut self) -> Result<&P::Value, P::Error>
+    where P::Value: Any, Self: Chained {
+        if chain::find::<P, Self>(self).is_none() {
+            return self.get_ref::<P>();
+        }
+
+        Ok(chain::find::<P, Self>(self).unwrap())
+    }
+
+    /// Return a copy of the plugin's produced value, recording any error
+    /// with `ErrorSink::record_error` instead of returning it.
+    ///
+    /// Failures aren't cached, so the plugin is evaluated again, and a
+    /// new error recorded, on every call until an evaluation succeeds.
+    /// A registered fallback is returned instead of recording an error.
+    ///
+    /// `P` is the plugin type.
+    fn get_or_record<P: Plugin<Self>>(&mut self) -> Option<P::Value>
+    where P::Value: Clone + Any, P::Error: Any, Self: ErrorSink {
+        match self.get::<P>() {
+            Ok(value) => Some(value),
+            Err(err) => { self.record_error(Box::new(err)); None }
+        }
+    }
+
+    /// Evaluate the plugin, failing with `EvalError::Recursion` instead if
+    /// it is already being evaluated through this method.
+    ///
+    /// The plugins being evaluated are tracked on a stack kept in the
+    /// extensions, so a plugin which calls `guarded_eval` for itself,
+    /// directly or through other plugins, gets an error rather than
+    /// recursing forever. The plugin is always evaluated and the value is
+    /// not cached.
+    ///
+    /// `P` is the plugin type.
+    fn guarded_eval<P: Plugin<Self>>(&mut self) -> Result<P::Value, EvalError<P::Error>>
+    where Self: ExtensibleStore {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let id = TypeId::of::<P>();
+        let stack = self.store_mut().entry_or_insert_with::<guard::Evaluating, _>(Vec::new);
+        if stack.contains(&id) {
+            return Err(EvalError::Recursion);
+        }
+        stack.push(id);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| P::eval(self)));
+        if let Some(stack) = self.store_mut().get_mut::<guard::Evaluating>() {
+            stack.retain(|&evaluating| evaluating != id);
+        }
+
+        match result {
+            Ok(result) => result.map_err(EvalError::Plugin),
+            Err(payload) => panic::resume_unwind(payload)
+        }
+    }
+
+    /// Limit how deeply evaluations through `get_depth_limited` may nest,
+    /// returning the previous limit, if any.
+    fn set_max_depth(&mut self, max_depth: usize) -> Option<usize>
+    where Self: ExtensibleStore {
+        self.store_mut().insert::<guard::MaxDepth>(max_depth)
+    }
+
+    /// Return a copy of the plugin's produced value, failing with
+    /// `DepthError::DepthLimitExceeded` instead of evaluating the plugin if
+    /// that would exceed the limit set with `set_max_depth`.
+    ///
+    /// Each evaluation by this method counts one level of depth for as long
+    /// as it runs, so plugins which resolve their dependencies through this
+    /// method get an error rather than overflowing the stack. Without a
+    /// limit, this behaves like `get`. Cached values are returned at any
+    /// depth.
+    ///
+    /// `P` is the plugin type.
+    fn get_depth_limited<P: Plugin<Self>>(&mut self) -> Result<P::Value, DepthError<P::Error>>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        use std::panic::{self, AssertUnwindSafe};
+
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        let depth = self.store().get::<guard::Depth>().cloned().unwrap_or(0);
+        if self.store().get::<guard::MaxDepth>().is_some_and(|&max_depth| depth >= max_depth) {
+            return Err(DepthError::DepthLimitExceeded);
+        }
+
+        self.store_mut().insert::<guard::Depth>(depth + 1);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.get::<P>()));
+        self.store_mut().insert::<guard::Depth>(depth);
+
+        match result {
+            Ok(result) => result.map_err(DepthError::Plugin),
+            Err(payload) => panic::resume_unwind(payload)
+        }
+    }
+
+    /// Return a copy of the plugin's produced value, allowing at most
+    /// `fuel` evaluations through this method while producing it.
+    ///
+    /// Each evaluation by this method spends one unit of fuel from a
+    /// counter kept in the extensions, shared with the evaluations nested
+    /// inside it. Plugins which resolve their dependencies through this
+    /// method therefore draw on the fuel of the outermost call, limited
+    /// further by their own `fuel`. Once it is spent, the plugin is not
+    /// evaluated and `FuelError::FuelExhausted` is returned. Cached values
+    /// are returned without spending anything.
+    ///
+    /// `P` is the plugin type.
+    fn get_with_fuel<P: Plugin<Self>>(&mut self, fuel: usize) -> Result<P::Value, FuelError<P::Error>>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        use std::panic::{self, AssertUnwindSafe};
+
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        let outer = self.store().get::<guard::Fuel>().cloned();
+        let fuel = outer.map_or(fuel, |outer| outer.min(fuel));
+        if fuel == 0 {
+            return Err(FuelError::FuelExhausted);
+        }
+
+        self.store_mut().insert::<guard::Fuel>(fuel - 1);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.get::<P>()));
+        let left = self.store_mut().remove::<guard::Fuel>().unwrap_or(0);
+        if let Some(outer) = outer {
+            self.store_mut().insert::<guard::Fuel>(outer - (fuel - left));
+        }
+
+        match result {
+            Ok(result) => result.map_err(FuelError::Plugin),
+            Err(payload) => panic::resume_unwind(payload)
+        }
+    }
+
+    /// Return a reference to the plugin's produced value, panicking in
+    /// debug builds if the plugin has to be evaluated a second time.
+    ///
+    /// Evaluations through this method are recorded in the extensions, and
+    /// the record outlives `invalidate`, so a value which was evaluated and
+    /// later dropped from the cache is reported. Failed evaluations are not
+    /// recorded. In release builds this is `get_ref`.
+    ///
+    /// `P` is the plugin type.
+    fn get_assert_once<P: Plugin<Self>>(&mut self) -> Result<&P::Value, P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        #[cfg(debug_assertions)]
+        {
+            if lookup::cached::<P, Self>(self).is_none() {
+                assert!(!self.store().contains::<guard::Evaluated<P>>(),
+                        "plugin {} was evaluated more than once", std::any::type_name::<P>());
+                self.ensure::<P>()?;
+                if alias::contains::<P, _>(self.store()) {
+                    self.store_mut().insert::<guard::Evaluated<P>>(());
+                }
+            }
+            Ok(macros::peek_evaluated::<P, _>(self))
+        }
+
+        #[cfg(not(debug_assertions))]
+        self.get_ref::<P>()
+    }
+
+    /// Make sure the plugin's value is cached, without producing it.
+    ///
+    /// The plugin will be created if it doesn't exist already.
+    /// If plugin creation fails an error is returned.
+    ///
+    /// `P` is the plugin type.
+    fn ensure<P: Plugin<Self>>(&mut self) -> Result<(), P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        self.get_mut::<P>().map(|_| ())
+    }
+
+    /// Return a shared, reference-counted handle to the plugin's produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already.
+    /// If plugin creation fails an error is returned.
+    ///
+    /// The handle is cached separately from the value returned by `get`,
+    /// `get_ref` and `get_mut`, so mixing the two evaluates the plugin twice.
+    fn get_rc<P: Plugin<Self>>(&mut self) -> Result<Rc<P::Value>, P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        if let Some(value) = self.store().get::<RcValue<P>>() {
+            return Ok(value.clone());
+        }
+
+        let value = Rc::new(lookup::eval::<P, Self>(self)?);
+        self.store_mut().insert::<RcValue<P>>(value.clone());
+        Ok(value)
+    }
+
+    /// Return a shared, atomically reference-counted handle to the plugin's
+    /// produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already, and its value
+    /// wrapped in an `Arc`; later calls clone the `Arc` instead of the value.
+    /// If plugin creation fails an error is returned.
+    ///
+    /// A value already cached for `get` is moved into the `Arc` instead of
+    /// being evaluated again, and the other methods read the value through
+    /// the `Arc`. Methods which replace, remove or mutably borrow the value,
+    /// such as `insert`, `take` and `get_mut`, move it back out of the `Arc`
+    /// first. If handles returned by this method are still alive at that
+    /// point, the value cannot be moved: the cache drops its handle and
+    /// treats the plugin as not cached, while the outstanding handles keep
+    /// the old value. To change a value without affecting the cache, use
+    /// `Arc::make_mut` on a handle.
+    fn get_shared<P: Plugin<Self>>(&mut self) -> Result<Arc<P::Value>, P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        if let Some(value) = self.store().get::<ArcValue<P>>() {
+            return Ok(value.clone());
+        }
+
+        lookup::refresh::<P, Self>(self);
+        let value = match alias::remove::<P, _>(self.store_mut()) {
+            Some(value) => Arc::new(value),
+            None => Arc::new(lookup::eval::<P, Self>(self)?)
+        };
+        self.store_mut().insert::<ArcValue<P>>(value.clone());
+        Ok(value)
+    }
+
+    /// Return a pinned reference to the plugin's produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already, and its value
+    /// boxed and pinned. The value never moves afterwards, until `invalidate`
+    /// drops it. If plugin creation fails an error is returned.
+    ///
+    /// `P` is the plugin type.
+    fn get_pinned<P: PinPlugin<Self>>(&mut self) -> Result<Pin<&P::Value>, P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        self.get_pinned_mut::<P>().map(Pin::into_ref)
+    }
+
+    /// Return a pinned mutable reference to the plugin's produced value.
+    ///
+    /// Behaves like `get_pinned`; mutation goes through `Pin<&mut _>`, so a
+    /// value which is `!Unpin` can't be moved out of the cache.
+    ///
+    /// `P` is the plugin type.
+    fn get_pinned_mut<P: PinPlugin<Self>>(&mut self) -> Result<Pin<&mut P::Value>, P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        if !self.store().contains::<PinnedValue<P>>() {
+            let value = Box::pin(lookup::eval::<P, Self>(self)?);
+            self.store_mut().insert::<PinnedValue<P>>(value);
+        }
+
+        Ok(self.store_mut().get_mut::<PinnedValue<P>>().unwrap().as_mut())
+    }
+
+    /// Return a weak handle to the plugin's reference-counted value.
+    ///
+    /// Behaves like `get_rc`, but the returned handle does not keep the value
+    /// alive: once the plugin is invalidated, `Weak::upgrade` returns `None`
+    /// unless another strong handle is still held.
+    fn get_weak<P: Plugin<Self>>(&mut self) -> Result<Weak<P::Value>, P::Error>
+    where P::Value: Any, Self: ExtensibleStore {
+        self.get_rc::<P>().map(|value| Rc::downgrade(&value))
+    }
+
+    /// Return a reference to the plugin's cached value, if any.
+    ///
+    /// The plugin is never evaluated.
+    fn peek<P: Key>(&self) -> Option<&P::Value>
+    where P::Value: Any, Self: ExtensibleStore {
+        self.store().get::<P>().or_else(|| self.store().get::<ArcValue<P>>().map(|value| &**value))
+    }
+
+    /// Return a reference to the plugin's cached value, assuming that the
+    /// plugin is cached.
+    ///
+    /// This skips the checks done by `get_ref` and `peek`, for callers which
+    /// have already made sure the plugin is cached. `assert_cached` can
+    /// check that assumption in debug builds.
+    ///
+    /// # Safety
+    ///
+    /// The plugin must have a cached value. Calling this otherwise is
+    /// undefined behavior.
+    unsafe fn get_ref_unchecked<P: Key>(&self) -> &P::Value
+    where P::Value: Any, Self: ExtensibleStore {
+        unsafe { self.store().get::<P>().unwrap_unchecked() }
+    }
+
+    /// Replace the plugin's cached value with the result of `f`, without
+    /// evaluating the plugin.
+    ///
+    /// `f` takes the cached value by value; on failure it hands the value
+    /// back together with its error, and the value is cached again
+    /// unchanged. Returns `Ok(true)` if the value was transformed and
+    /// `Ok(false)` if none was cached.
+    fn try_map_cached<P: Key, Err, F>(&mut self, f: F) -> Result<bool, Err>
+    where P::Value: Any, F: FnOnce(P::Value) -> Result<P::Value, (P::Value, Err)>, Self: ExtensibleStore {
+        lookup::unshare::<P, _>(self.store_mut());
+        let value = match self.store_mut().remove::<P>() {
+            Some(value) => value,
+            None => return Ok(false)
+        };
+
+        match f(value) {
+            Ok(value) => {
+                self.store_mut().remove::<RcValue<P>>();
+                self.store_mut().remove::<ArcValue<P>>();
+                self.store_mut().insert::<P>(value);
+                Ok(true)
+            },
+            Err((value, err)) => {
+                self.store_mut().insert::<P>(value);
+                Err(err)
+            }
+        }
+    }
+
+    /// Return a reference to the plugin's cached value, caching `default`
+    /// first if there is none.
+    ///
+    /// Unlike `get_ref`, the plugin is never evaluated.
+    fn get_ref_or_insert<P: Key>(&mut self, default: P::Value) -> &P::Value
+    where P::Value: Any, Self: ExtensibleStore {
+        self.store_mut().entry_or_insert_with::<P, _>(|| default)
+    }
+
+    /// Return a reference to the plugin's cached value, caching the result
+    /// of `init` first if there is none.
+    ///
+    /// If `init` fails nothing is cached and its error is returned. Like
+    /// `get_ref_or_insert`, the plugin is never evaluated.
+    fn get_ref_or_try_init<P: Key, Err, F>(&mut self, init: F) -> Result<&P::Value, Err>
+    where P::Value: Any, F: FnOnce() -> Result<P::Value, Err>, Self: ExtensibleStore {
+        if self.store().contains::<P>() {
+            return Ok(self.store().get::<P>().unwrap());
+        }
+
+        let value = init()?;
+        Ok(self.store_mut().entry_or_insert_with::<P, _>(|| value))
+    }
+
+    /// Return a mutable reference to the plugin's cached value, caching
+    /// `P::Value::default()` first if there is none.
+    ///
+    /// Suits accumulators such as counters and buffers. The plugin is
+    /// never evaluated.
+    fn accumulate<P: Key>(&mut self) -> &mut P::Value
+    where P::Value: Default + Any, Self: ExtensibleStore {
+        self.store_mut().entry_or_insert_with::<P, _>(Default::default)
+    }
+
+    /// Assert that the plugin has a cached value, panicking with the
+    /// plugin's type name if it does not.
+    ///
+    /// The check only runs in builds with debug assertions enabled; in
+    /// release builds this does nothing. The plugin is never evaluated.
+    fn assert_cached<P: Key>(&self)
+    where P::Value: Any, Self: ExtensibleStore {
+        debug_assert!(self.store().contains::<P>(),
+                      "plugin {} is not cached", std::any::type_name::<P>());
+    }
+
+    /// Return a reference to the cached value stored under the key `V`,
+    /// if that value is itself of type `V`.
+    ///
+    /// This suits the common self-keyed pattern, where a plugin type is its
+    /// own value (`impl Key for V { type Value = V; }`), in code which only
+    /// knows the value's type. The stored value's type is checked, so for
+    /// keys whose value has another type this returns `None`.
+    fn get_value_typed<V: Any>(&self) -> Option<&V>
+    where Self: Extensible {
+        let data = unsafe { self.extensions().data() };
+        data.get(&TypeId::of::<V>()).and_then(|value| {
+            let value: &dyn Any = &**value;
+            value.downcast_ref::<V>()
+        })
+    }
+
+    /// Cache a value for `P` without evaluating the plugin.
+    ///
+    /// The value counts as computed at generation 0. Returns the previously
+    /// cached value, if any, after running its eviction hook. Like the other
+    /// methods of this trait, this follows an alias registered for `P`.
+    fn insert<P: Key>(&mut self, value: P::Value) -> Option<P::Value>
+    where P::Value: Any, Self: ExtensibleStore {
+        lookup::unshare::<P, _>(self.store_mut());
+        version::clear::<P, _>(self.store_mut());
+        let mut old = alias::replace::<P, _>(self.store_mut(), value);
+        if let Some(ref mut old) = old {
+            evict::notify::<P, _>(self.store(), old);
+        }
+        old
+    }
+
+    /// Remove and return the plugin's cached value, without evaluating it.
+    ///
+    /// Unlike `invalidate`, the handle produced by `get_rc` is left cached.
+    fn take<P: Key>(&mut self) -> Option<P::Value>
+    where P::Value: Any, Self: ExtensibleStore {
+        lookup::unshare::<P, _>(self.store_mut());
+        version::clear::<P, _>(self.store_mut());
+        let mut value = alias::remove::<P, _>(self.store_mut())?;
+        evict::notify::<P, _>(self.store(), &mut value);
+        Some(value)
+    }
+
+    /// Remove the plugin's cached value, so the plugin is evaluated again
+    /// the next time it is requested.
+    ///
+    /// Also drops the cache's handles to the values produced by `get_rc`
+    /// and `get_shared`, and the value pinned by `get_pinned`. Returns the
+    /// value cached for `get`, if there was one.
+    fn invalidate<P: Key>(&mut self) -> Option<P::Value>
+    where P::Value: Any, Self: ExtensibleStore {
+        evict::discard::<P, _>(self.store_mut())
+    }
+
+    /// Remove everything stored in the extensions, running the eviction
+    /// hooks of registered plugins first.
+    ///
+    /// This also removes registrations, such as fallbacks and eviction hooks.
+    fn clear_extensions(&mut self)
+    where Self: Extensible {
+        evict::evict_all(self.extensions_mut());
+        self.extensions_mut().clear();
+    }
+
+    /// Remove everything stored in the extensions, passing each entry to
+    /// `f` with its key's `TypeId` before it is dropped.
+    ///
+    /// Eviction hooks of registered plugins run on their values first, so
+    /// `f` can perform further teardown. Like `clear_extensions`, this also
+    /// removes registrations, which `f` receives too.
+    fn clear_with<F>(&mut self, f: F)
+    where F: FnMut(TypeId, Box<dyn Any>), Self: Extensible {
+        evict::drain(self.extensions_mut(), f)
+    }
+
+    /// Return how many entries the extensions can hold without
+    /// reallocating.
+    ///
+    /// Compare against `extensions().len()` to spot a map which has grown
+    /// far beyond what it currently holds.
+    fn extensions_capacity(&self) -> usize
+    where Self: Extensible {
+        // Only the capacity is read, which cannot break the map's invariants.
+        unsafe { self.extensions().data() }.capacity()
+    }
+
+    /// Return the `TypeId`s of every key currently stored in the
+    /// extensions, to compare against later.
+    ///
+    /// Besides cached plugins, this includes the keys the crate uses
+    /// internally, such as registered fallbacks.
+    fn checkpoint(&self) -> HashSet<TypeId>
+    where Self: Extensible {
+        // Only the keys are read, which cannot break the map's invariants.
+        unsafe { self.extensions().data() }.keys().cloned().collect()
+    }
+
+    /// Adopt every entry of `map`, such as one built by `export!`, into
+    /// this type's extensions.
+    ///
+    /// When an entry is already present, it is replaced if `overwrite` is
+    /// `true`, running the replaced value's eviction hook, and skipped
+    /// otherwise. Entries are moved as-is, so any registrations `map`
+    /// holds, such as fallbacks, are adopted too.
+    fn restore_from(&mut self, mut map: TypeMap, overwrite: bool)
+    where Self: Extensible {
+        // Entries are moved between maps under their own keys, which
+        // cannot break either map's invariants.
+        for (id, value) in unsafe { map.data_mut() }.drain() {
+            let present = unsafe { self.extensions().data() }.contains_key(&id);
+            if present && !overwrite { continue }
+            if present { evict::evict_id(self.extensions_mut(), id) }
+            unsafe { self.extensions_mut().data_mut() }.insert(id, value);
+        }
+    }
+
+    /// Replace this type's extensions with a new, empty `TypeMap`,
+    /// returning the previous one.
+    ///
+    /// Unlike `clear_extensions`, no eviction hooks are run: the old
+    /// values, and registrations such as fallbacks, are handed to the
+    /// caller, who can inspect them or drop them elsewhere.
+    fn reinitialize(&mut self) -> TypeMap
+    where Self: Extensible {
+        std::mem::replace(self.extensions_mut(), TypeMap::new())
+    }
+
+    /// Replace this type's extensions with `map`, for building contexts
+    /// whose cache is seeded at construction, e.g.
+    /// `Request::new().with_extensions(map)`.
+    ///
+    /// Eviction hooks registered in the replaced extensions are run.
+    fn with_extensions(mut self, map: TypeMap) -> Self
+    where Self: Extensible + Sized {
+        evict::evict_all(self.extensions_mut());
+        *self.extensions_mut() = map;
+        self
+    }
+
+    /// Register `P`'s eviction hook, to be run whenever its cached value
+    /// leaves the cache.
+    fn register_evictable<P: EvictablePlugin>(&mut self)
+    where Self: Extensible {
+        evict::register::<P>(self.extensions_mut())
+    }
+
+    /// Make `get::<Old>()`, and the methods built on it, resolve to the
+    /// value cached for `New`, such as when `Old` has been renamed to `New`.
+    ///
+    /// The alias is checked before evaluation: if `New` is cached, its value
+    /// is returned and `Old` is not evaluated. Otherwise `Old` is evaluated
+    /// and its value cached for `New`, so both keys share one cached value.
+    /// Methods which read the cache without going through `get_mut`, such
+    /// as `peek`, are not redirected.
+    fn register_alias<Old: Key, New: Key<Value = Old::Value>>(&mut self)
+    where Self: ExtensibleStore {
+        alias::register::<Old, New, _>(self.store_mut())
+    }
+
+    /// Register a value to be used in place of `P` when its evaluation fails.
+    ///
+    /// The fallback is not a preset: `get` still evaluates the plugin and
+    /// returns its value when evaluation succeeds. The fallback is never cached
+    /// as `P`'s value, so evaluation is retried on the next call, and changes
+    /// made through `get_mut` modify the registered fallback itself.
+    ///
+    /// Returns the previously registered fallback, if any.
+    fn set_fallback<P: Key>(&mut self, fallback: P::Value) -> Option<P::Value>
+    where P::Value: Any, Self: ExtensibleStore {
+        self.store_mut().insert::<Fallback<P>>(fallback)
+    }
+
+    /// Start evaluating a plugin on a background thread.
+    ///
+    /// The next call to `get`, `get_ref` or `get_mut` for `P` joins the
+    /// background evaluation instead of evaluating the plugin again. If the
+    /// plugin is already cached or already being prefetched, this does nothing.
+    ///
+    /// If the background evaluation fails, its error is discarded and the
+    /// plugin is evaluated again on the calling thread to report it.
+    #[cfg(feature = "thread")]
+    fn prefetch<P: PrefetchPlugin<Self>>(&mut self)
+    where P::Value: Send, P::Error: Send + 'static, Self: ExtensibleStore {
+        if self.store().contains::<P>() { return }
+
+        prefetch::spawn::<P, Self>(self)
+    }
+
+    /// Return a copy of the plugin's cached value immediately, refreshing it
+    /// on a background thread once it is older than `P::ttl()`.
+    ///
+    /// On a miss, the plugin is evaluated on the calling thread and cached
+    /// together with the time of evaluation, as for `get_ttl`. A stale
+    /// value keeps being returned until a later call finds the refresh
+    /// finished and caches its value; a failed refresh is discarded and
+    /// the stale value kept. Only one refresh runs at a time.
+    ///
+    /// The refresh runs `P::eval_input`, so its value must be `Send`, and
+    /// values are returned by copy, so it must also be `Clone`. The refresh's
+    /// errors are dropped on the background thread and need not be `Send`.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "thread")]
+    fn get_stale_while_revalidate<P>(&mut self) -> Result<P::Value, P::Error>
+    where P: PrefetchPlugin<Self> + TtlPlugin<Self>, P::Value: Clone + Send,
+          P::Error: 'static, Self: Extensible {
+        let now = Instant::now();
+        if let Some(value) = prefetch::finished_refresh::<P, _>(self.extensions_mut()) {
+            self.insert::<P>(value);
+            version::stamp::<P, _>(self.extensions_mut());
+            self.extensions_mut().insert::<ttl::CachedAt<P>>(now);
+        }
+
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            if ttl::is_expired::<P, Self>(self.extensions(), now) {
+                prefetch::spawn_refresh::<P, Self>(self);
+            }
+            return Ok(value);
+        }
+
+        let value = self.get::<P>()?;
+        if alias::contains::<P, _>(self.extensions()) {
+            ttl::register::<P, Self>(self.extensions_mut());
+            self.extensions_mut().insert::<ttl::CachedAt<P>>(now);
+        }
+        Ok(value)
+    }
+
+    /// Return a copy of an asynchronous plugin's produced value, blocking
+    /// the current thread until its evaluation completes.
+    ///
+    /// The plugin's future is driven by a minimal executor which parks the
+    /// thread while the future is pending. The value is cached as for `get`.
+    ///
+    /// This must not be called from within an async runtime: blocking one
+    /// of its threads can stall or deadlock other tasks.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "async")]
+    fn get_blocking<P: AsyncPlugin<Self>>(&mut self) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok(value);
+        }
+
+        match lookup::eval_with::<P, Self, _, _>(self, |ext| future::block_on(P::eval(ext))) {
+            Ok(value) => Ok(lookup::store::<P, Self>(self, value).clone()),
+            Err(err) => self.store().get::<Fallback<P>>().cloned().ok_or(err)
+        }
+    }
+
+    /// Register `P` so that its cached value is returned by `collect_as::<Dyn>`.
+    ///
+    /// Registering the same plugin more than once has no further effect.
+    fn register_as<P: TraitPlugin<Dyn>, Dyn: ?Sized + 'static>(&mut self)
+    where Self: Extensible {
+        collect::register::<P, Dyn>(self.extensions_mut())
+    }
+
+    /// Return references to the cached values of all plugins registered
+    /// under `Dyn`, in registration order.
+    ///
+    /// Registered plugins which are not cached are skipped; nothing is evaluated.
+    fn collect_as<Dyn: ?Sized + 'static>(&self) -> Vec<&Dyn>
+    where Self: Extensible {
+        collect::collect::<Dyn>(self.extensions())
+    }
+
+    /// Iterate over the type names and cached values of all plugins
+    /// registered under `Dyn`, in registration order.
+    ///
+    /// Like `collect_as`, registered plugins which are not cached are
+    /// skipped and nothing is evaluated.
+    fn iter_registered<Dyn: ?Sized + 'static>(&self) -> impl Iterator<Item = (&'static str, &Dyn)>
+    where Self: Extensible {
+        collect::iter::<Dyn>(self.extensions())
+    }
+
+    /// Register `P` as a member of the named group, so that `iter_group`
+    /// yields its cached value.
+    ///
+    /// A plugin can belong to several groups. Registering the same plugin
+    /// in a group more than once has no further effect.
+    fn register_in_group<P: Key>(&mut self, group: &'static str)
+    where Self: Extensible {
+        group::register::<P>(self.extensions_mut(), group)
+    }
+
+    /// Iterate over the cached values of the plugins registered in the
+    /// named group whose value type is `T`, in registration order.
+    ///
+    /// Members which are not cached, or whose values are of another type,
+    /// are skipped; nothing is evaluated.
+    fn iter_group<'a, T: Any>(&'a self, group: &str) -> impl Iterator<Item = &'a T> + 'a
+    where Self: Extensible {
+        group::iter::<T>(self.extensions(), group)
+    }
+
+    /// Invalidate every cached plugin registered in the named group, as
+    /// with `invalidate`.
+    ///
+    /// Returns the number of plugins whose cached values were removed.
+    fn invalidate_group(&mut self, group: &str) -> usize
+    where Self: Extensible {
+        group::invalidate(self.extensions_mut(), group)
+    }
+
+    /// Count the cached plugins in each group registered with
+    /// `register_in_group`.
+    ///
+    /// Every group with at least one registered plugin is listed, even if
+    /// none of its plugins are cached. Requires the `stats` feature.
+    #[cfg(feature = "stats")]
+    fn group_counts(&self) -> HashMap<&'static str, usize>
+    where Self: Extensible {
+        group::counts(self.extensions())
+    }
+
+    /// Register `P` so that its cached value is included in `extensions_hash`.
+    ///
+    /// Registering the same plugin more than once has no further effect.
+    fn register_hashable<P: HashablePlugin>(&mut self)
+    where P::Value: Hash, Self: Extensible {
+        hash::register::<P>(self.extensions_mut())
+    }
+
+    /// Register `P` so that its cached values are compared by `diff_values`.
+    ///
+    /// Registering the same plugin more than once has no further effect.
+    fn register_comparable<P: ComparablePlugin>(&mut self)
+    where P::Value: PartialEq, Self: Extensible {
+        compare::register::<P>(self.extensions_mut())
+    }
+
+    /// Hash the cached values of every plugin registered with
+    /// `register_hashable`.
+    ///
+    /// Values are combined in `TypeId` order, so the hash doesn't depend on
+    /// the order of registration or evaluation. Two contexts caching the
+    /// same registered plugins with equal values hash equally within a
+    /// build; the hash is not stable across builds.
+    fn extensions_hash(&self) -> u64
+    where Self: Extensible {
+        hash::hash(self.extensions())
+    }
+
+    /// Start counting evaluations of `P` by the methods which cache its
+    /// value, such as `get`, `get_ref` and `get_shared`, resetting any
+    /// count so far.
+    ///
+    /// Requires the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    fn track_evals<P: Key>(&mut self)
+    where Self: ExtensibleStore {
+        self.store_mut().insert::<test_util::EvalCount<P>>(0);
+    }
+
+    /// Return how many times `P` has been evaluated since `track_evals`
+    /// was called for it, or 0 if it isn't tracked.
+    ///
+    /// Requires the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    fn eval_count<P: Key>(&self) -> usize
+    where Self: ExtensibleStore {
+        self.store().get::<test_util::EvalCount<P>>().cloned().unwrap_or(0)
+    }
+
+    /// Register `P` to be listed by `extension_report`.
+    ///
+    /// Registering the same plugin more than once has no further effect.
+    fn register_for_report<P: Key>(&mut self)
+    where Self: Extensible {
+        report::register::<P>(self.extensions_mut())
+    }
+
+    /// List the type name of every plugin registered with
+    /// `register_for_report`, in registration order, together with whether
+    /// it currently has a cached value.
+    ///
+    /// Type names come from `std::any::type_name` and are meant for
+    /// diagnostics only; their exact form is not stable.
+    fn extension_report(&self) -> Vec<(&'static str, bool)>
+    where Self: Extensible {
+        report::report(self.extensions())
+    }
+
+    /// Register `P`'s dependencies, so that refreshing any of them with
+    /// `recompute_dependents` invalidates `P`.
+    fn register_dependent<P: DependentPlugin<Self>>(&mut self)
+    where Self: Extensible {
+        dependents::register::<P, Self>(self.extensions_mut())
+    }
+
+    /// Invalidate every registered plugin which depends on `P`, directly
+    /// or transitively, so that each is evaluated again on next access.
+    ///
+    /// `P` itself is left untouched. Returns the number of dependents
+    /// whose cached values were removed.
+    fn recompute_dependents<P: Key>(&mut self) -> usize
+    where Self: Extensible {
+        dependents::invalidate_dependents(self.extensions_mut(), TypeId::of::<P>())
+    }
+
+    /// Register `P` to be evaluated by `warm_up_sorted`.
+    ///
+    /// Registering the same plugin more than once has no further effect.
+    fn register_warm_up<P: DependentPlugin<Self>>(&mut self)
+    where P::Error: Any, Self: Extensible + 'static {
+        dependents::register_warm_up::<P, Self>(self.extensions_mut())
+    }
+
+    /// Evaluate every plugin registered with `register_warm_up` which is
+    /// not already cached, each after the registered plugins it depends on,
+    /// and return the `TypeId`s of all registered plugins in that order.
+    ///
+    /// The order depends only on the registrations and the declared
+    /// dependencies: plugins are visited in registration order, and each
+    /// is preceded by its registered dependencies. Dependencies which are
+    /// not registered are left for the dependents themselves to evaluate.
+    ///
+    /// A dependency cycle among registered plugins is reported as
+    /// `WarmUpError::Recursion` before anything is evaluated. Evaluation
+    /// stops at the first failure, which is reported as `WarmUpError::Plugin`
+    /// with the plugin's `TypeId` and boxed `Plugin::Error`.
+    fn warm_up_sorted(&mut self) -> Result<Vec<TypeId>, WarmUpError>
+    where Self: Extensible + 'static {
+        dependents::warm_up_sorted(self)
+    }
+
+    /// Register `P` to be re-evaluated by `recompute_all`.
+    ///
+    /// Registering the same plugin more than once has no further effect.
+    fn register_recompute<P: Plugin<Self>>(&mut self)
+    where P::Error: Any, Self: Extensible + 'static {
+        recompute::register::<P, Self>(self.extensions_mut())
+    }
+
+    /// Re-evaluate every plugin registered with `register_recompute` which
+    /// currently has a cached value, replacing the cached value with the
+    /// new one. Plugins which are not cached are not evaluated.
+    ///
+    /// A plugin whose evaluation fails keeps its previous value. Returns the
+    /// `TypeId` and boxed `Plugin::Error` of each plugin which failed, in
+    /// registration order.
+    fn recompute_all(&mut self) -> Vec<(TypeId, Box<dyn Any>)>
+    where Self: Extensible + 'static {
+        recompute::recompute_all(self)
+    }
+
+    /// Register `P` under `name`, so it can be computed by `compute_named`.
+    ///
+    /// Registering another plugin under the same name replaces it.
+    fn register_named<P: Plugin<Self>>(&mut self, name: &'static str)
+    where P::Error: Any, Self: Extensible + 'static {
+        named::register::<P, Self>(self.extensions_mut(), name)
+    }
+
+    /// Evaluate a once-off instance of the plugin registered under `name`
+    /// with `register_named`, returning its boxed value or boxed
+    /// `Plugin::Error`.
+    ///
+    /// The value is returned without being cached. Returns `None` if no
+    /// plugin is registered under `name`.
+    fn compute_named(&mut self, name: &str) -> Option<Result<Box<dyn Any>, Box<dyn Any>>>
+    where Self: Extensible + 'static {
+        named::compute_named(self, name)
+    }
+
+    /// Stream the items of a streaming plugin.
+    ///
+    /// If the plugin caches its sequence and a previous stream was fully
+    /// drained, the cached items are replayed. Otherwise the plugin is
+    /// evaluated, and the returned stream borrows this type until dropped.
+    ///
+    /// `P` is the plugin type.
+    fn stream<P: StreamingPlugin<Self>>(&mut self) -> Stream<'_, P, Self>
+    where P::Value: Clone, Self: ExtensibleStore {
+        stream::stream::<P, Self>(self)
+    }
+
+    /// Create a view borrowing from this type.
+    ///
+    /// Views are never cached; `P::view` runs on every call.
+    fn view<P: ViewPlugin<Self>>(&self) -> P::View<'_> {
+        P::view(self)
+    }
+
+    /// Evaluate a plugin and cache its value in `store` rather than in
+    /// this type's own extensions.
+    ///
+    /// The plugin is always evaluated; any value already in `store` is replaced.
+    fn compute_into_store<P: Plugin<Self>, S: ExtensionStore + ?Sized>(&mut self, store: &mut S)
+        -> Result<(), P::Error> {
+        let value = P::eval(self)?;
+        store.insert::<P>(value);
+        Ok(())
+    }
+
+    /// Register `P` so that `insert_boxed` can cache type-erased values for it.
+    fn register_boxed<P: Key>(&mut self)
+    where Self: Extensible {
+        boxed::register::<P>(self.extensions_mut())
+    }
+
+    /// Cache a type-erased value for the plugin whose `TypeId` is `id`,
+    /// such as one produced elsewhere by `compute_boxed`.
+    ///
+    /// The plugin must have been registered with `register_boxed`. If it
+    /// wasn't, or if the value is not of the plugin's value type, the box
+    /// is returned unchanged. A replaced value runs its eviction hook.
+    fn insert_boxed(&mut self, id: TypeId, value: Box<dyn Any>) -> Result<(), Box<dyn Any>>
+    where Self: Extensible {
+        boxed::insert_boxed(self.extensions_mut(), id, value)
+    }
+
+    /// Cache type-erased values, as with `insert_boxed`, but only for
+    /// plugins which have no cached value yet.
+    ///
+    /// Values for plugins which are already cached, which were not
+    /// registered with `register_boxed`, or which have the wrong type are
+    /// dropped. Returns the number of values cached.
+    fn insert_missing_boxed<I>(&mut self, values: I) -> usize
+    where I: IntoIterator<Item = (TypeId, Box<dyn Any>)>, Self: Extensible {
+        boxed::insert_missing(self.extensions_mut(), values)
+    }
+
+    /// Evaluate a plugin without caching it, returning its value boxed as
+    /// `dyn Any` for a later `insert_boxed` under `TypeId::of::<P>()`.
+    fn compute_boxed<P: Plugin<Self>>(&mut self) -> Result<Box<dyn Any>, P::Error> {
+        self.compute::<P>().map(|value| Box::new(value) as Box<dyn Any>)
+    }
+
+    /// Return a copy of the plugin's produced value, together with the
+    /// auxiliary output of its evaluation.
+    ///
+    /// The auxiliary output is `Some` only when this call evaluated the
+    /// plugin; on a cache hit it is `None`. Only the value is cached. If
+    /// plugin creation fails an error is returned.
+    ///
+    /// `P` is the plugin type.
+    #[allow(clippy::type_complexity)]
+    fn get_with_aux<P: DiagnosticPlugin<Self>>(&mut self) -> Result<(P::Value, Option<P::Aux>), P::Error>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        if let Some(value) = lookup::cloned::<P, Self>(self) {
+            return Ok((value, None));
+        }
+
+        let (value, aux) = lookup::eval_with::<P, Self, _, _>(self, P::eval)?;
+        lookup::store::<P, Self>(self, value.clone());
+        Ok((value, Some(aux)))
+    }
+
+    /// Evaluate a plugin with a runtime argument.
+    ///
+    /// The value is returned without being cached.
+    fn compute_with_arg<P: ArgPlugin<Self>>(&mut self, arg: P::Arg) -> Result<P::Value, P::Error> {
+        P::eval(self, arg)
+    }
+
+    /// Return a reference to a keyed plugin's value for `arg`.
+    ///
+    /// The plugin will be evaluated for `arg` if that key isn't cached
+    /// already. If plugin creation fails an error is returned.
+    ///
+    /// `P` is the plugin type.
+    fn get_keyed_ref<P: KeyedPlugin<Self>>(&mut self, arg: P::Arg) -> Result<&P::Value, P::Error>
+    where Self: ExtensibleStore {
+        if keyed::get::<P, Self, _>(self.store(), &arg).is_none() {
+            let value = P::eval(self, &arg)?;
+            return Ok(keyed::insert::<P, Self, _>(self.store_mut(), arg, value));
+        }
+
+        Ok(keyed::get::<P, Self, _>(self.store(), &arg).unwrap())
+    }
+
+    /// Evaluate a keyed plugin for each of `keys` not cached yet, caching
+    /// the values.
+    ///
+    /// Keys are evaluated in order. The first failure stops the others,
+    /// and its error is returned; values evaluated before it stay cached.
+    ///
+    /// `P` is the plugin type.
+    fn populate_keyed<P: KeyedPlugin<Self>, I>(&mut self, keys: I) -> Result<(), P::Error>
+    where I: IntoIterator<Item = P::Arg>, Self: ExtensibleStore {
+        for arg in keys {
+            if keyed::get::<P, Self, _>(self.store(), &arg).is_some() { continue }
+
+            let value = P::eval(self, &arg)?;
+            keyed::insert::<P, Self, _>(self.store_mut(), arg, value);
+        }
+        Ok(())
+    }
+
+    /// Evaluate a pure keyed plugin for each of `keys` not cached yet,
+    /// spreading the evaluations across threads, and cache the values.
+    ///
+    /// Every missing key is evaluated once, even if it appears in `keys`
+    /// more than once. The values which succeed are cached and, if any
+    /// evaluation fails, the error of the first failing key in `keys` is
+    /// returned.
+    ///
+    /// Requires the `parallel` feature.
+    ///
+    /// `P` is the plugin type.
+    #[cfg(feature = "parallel")]
+    fn populate_keyed_parallel<P: PureKeyedPlugin<Self>, I>(&mut self, keys: I) -> Result<(), P::Error>
+    where I: IntoIterator<Item = P::Arg>, P::Arg: Send, P::Value: Send, P::Error: Send,
+          Self: ExtensibleStore {
+        let keys: Vec<P::Arg> = keys.into_iter()
+            .filter(|arg| keyed::get::<P, Self, _>(self.store(), arg).is_none())
+            .collect();
+        let first: Vec<bool> = {
+            let mut seen = HashSet::new();
+            keys.iter().map(|arg| seen.insert(arg)).collect()
+        };
+        let missing = keys.into_iter().zip(first)
+            .filter_map(|(arg, first)| if first { Some(arg) } else { None })
+            .collect();
+
+        let mut error = None;
+        for (arg, result) in keyed::eval_parallel::<P, Self>(missing) {
+            match result {
+                Ok(value) => { keyed::insert::<P, Self, _>(self.store_mut(), arg, value); },
+                Err(err) => { error.get_or_insert(err); }
+            }
+        }
+        error.map_or(Ok(()), Err)
+    }
+
+    /// Create and evaluate a once-off instance of a plugin.
+    fn compute<P: Plugin<Self>>(&mut self) -> Result<P::Value, P::Error> {
+        <P as Plugin<Self>>::eval(self)
+    }
+
+    /// Return a clone of the plugin's cached value, or evaluate a once-off
+    /// instance of the plugin if there is none.
+    ///
+    /// On a miss the value is returned without being cached, like
+    /// `compute`, and no fallback is consulted.
+    fn compute_if_absent<P: Plugin<Self>>(&mut self) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Any, Self: ExtensibleStore {
+        match lookup::cloned::<P, Self>(self) {
+            Some(value) => Ok(value),
+            None => self.compute::<P>()
+        }
+    }
+}
+
+struct Fallback<P>(PhantomData<P>);
+
+impl<P: Key> Key for Fallback<P> { type Value = P::Value; }
+
+struct Outcome<P, E>(PhantomData<(P, E)>);
+
+impl<P: Key, E: Any> Key for Outcome<P, E> { type Value = Result<P::Value, E>; }
+
+struct RcValue<P>(PhantomData<P>);
+
+impl<P: Key> Key for RcValue<P> { type Value = Rc<P::Value>; }
+
+struct ArcValue<P>(PhantomData<P>);
+
+impl<P: Key> Key for ArcValue<P> { type Value = Arc<P::Value>; }
+
+struct PinnedValue<P>(PhantomData<P>);
+
+impl<P: Key> Key for PinnedValue<P> { type Value = Pin<Box<P::Value>>; }
+
+/// Create an empty `TypeMap` with room for at least `capacity` values.
+pub fn new_extensions_with_capacity(capacity: usize) -> TypeMap {
+    let mut map = TypeMap::new();
+    // Reserving space cannot break the map's invariants.
+    unsafe { map.data_mut().reserve(capacity) };
+    map
+}
+
+/// Return a reference to the value cached for `P` in `map`, if any.
+///
+/// This reads from any `TypeMap`, such as a copy of a context's
+/// extensions captured earlier, without needing an extended type.
+/// Nothing is evaluated.
+pub fn get_from_map<P: Key>(map: &TypeMap) -> Option<&P::Value> {
+    map.get::<P>()
+}
+
+/// Compare the keys stored in two `TypeMap`s, such as copies of a context's
+/// extensions taken before and after some work.
+///
+/// Returns the `TypeId`s of the keys present only in `after`, then those
+/// present only in `before`, each in no particular order. Every stored key
+/// is compared, including those the crate uses internally, such as
+/// registered fallbacks; values are not compared.
+pub fn diff_type_ids(before: &TypeMap, after: &TypeMap) -> (Vec<TypeId>, Vec<TypeId>) {
+    // Only the keys are read, which cannot break the maps' invariants.
+    let (before, after) = unsafe { (before.data(), after.data()) };
+    let added = after.keys().filter(|id| !before.contains_key(id)).cloned().collect();
+    let removed = before.keys().filter(|id| !after.contains_key(id)).cloned().collect();
+    (added, removed)
+}
+
+/// Compare the values cached in two `TypeMap`s, such as copies of a
+/// context's extensions taken before and after some work.
+///
+/// Returns the type names of the plugins registered with
+/// `Pluggable::register_comparable`, in either map, whose cached values
+/// differ: unequal, or cached in only one of the maps. Unregistered
+/// plugins are never compared.
+pub fn diff_values(before: &TypeMap, after: &TypeMap) -> Vec<&'static str> {
+    compare::diff_values(before, after)
+}
+
+/// Evaluate a plugin against a projection of an extended type.
+///
+/// `compute` borrows the whole extended type mutably for as long as the
+/// plugin runs. When a plugin only needs a few fields, define a small
+/// projection type borrowing just those fields, implement `Plugin` for
+/// the projection and evaluate it here, leaving the other fields free.
+///
+/// If the plugin itself calls `get` on its context, the projection must
+/// also implement `Extensible` and `Pluggable`, usually by owning a fresh
+/// `TypeMap` which lives only as long as the projection.
+pub fn compute_from<P: Plugin<Ctx>, Ctx>(mut ctx: Ctx) -> Result<P::Value, P::Error> {
+    P::eval(&mut ctx)
+}
+
+#[cfg(test)]
+mod test {
+    extern crate void;
+
+    use test::void::{Void, ResultVoidExt};
+
+    use std::any::TypeId;
+
+    use typemap::{TypeMap, Key};
+    use super::{Extensible, Plugin, Pluggable, DependentPlugin, PinPlugin};
+    use super::{compute_from, new_extensions_with_capacity};
+
+    struct Extended {
+        map: TypeMap
+    }
+
+    impl Extended {
+        fn new() -> Extended {
+            Extended { map: TypeMap::new() }
+        }
+    }
+
+    impl Extensible for Extended {
+        fn extensions(&self) -> &TypeMap { &self.map }
+        fn extensions_mut(&mut self) -> &mut TypeMap { &mut self.map }
+    }
+
+    impl Pluggable for Extended {}
+
+    macro_rules! generate_simple_plugin (
+        ($t:ty, $v:ident, $v2:expr) => {
+            #[derive(PartialEq, Debug, Clone)]
+            struct $v(i32);
+
+            impl Key for $t { type Value = $t; }
+
+            impl Plugin<Extended> for $t {
+                type Error = Void;
+
+                fn eval(_: &mut Extended) -> Result<$t, Void> {
+                    Ok($v($v2))
+                }
+            }
+        }
+    );
+
+    generate_simple_plugin!(One, One, 1);
+    generate_simple_plugin!(Two, Two, 2);
+    generate_simple_plugin!(Three, Three, 3);
+    generate_simple_plugin!(Four, Four, 4);
+    generate_simple_plugin!(Five, Five, 5);
+    generate_simple_plugin!(Six, Six, 6);
+    generate_simple_plugin!(Seven, Seven, 7);
+    generate_simple_plugin!(Eight, Eight, 8);
+    generate_simple_plugin!(Nine, Nine, 9);
+    generate_simple_plugin!(Ten, Ten, 10);
+
+    struct IntKey;
+
+    impl Key for IntKey { type Value = i32; }
+
+    #[test] fn test_simple() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get::<One>(),   Ok(One(1)));
+        assert_eq!(extended.get::<Two>(),   Ok(Two(2)));
+        assert_eq!(extended.get::<Three>(), Ok(Three(3)));
+    }
+
+    #[test] fn test_resize() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        extended.get::<Two>().void_unwrap();
+        extended.get::<Three>().void_unwrap();
+        extended.get::<Four>().void_unwrap();
+        extended.get::<Five>().void_unwrap();
+        extended.get::<Six>().void_unwrap();
+        extended.get::<Seven>().void_unwrap();
+        extended.get::<Eight>().void_unwrap();
+        extended.get::<Nine>().void_unwrap();
+        extended.get::<Ten>().void_unwrap();
+        assert_eq!(extended.get_ref::<One>(), Ok(&One(1)))
+    }
+
+    #[test] fn test_with_capacity() {
+        let mut extended = Extended { map: new_extensions_with_capacity(10) };
+        let capacity = extended.extensions_capacity();
+        assert!(capacity >= 10);
+
+        extended.get::<One>().void_unwrap();
+        extended.get::<Ten>().void_unwrap();
+        assert_eq!(extended.extensions_capacity(), capacity);
+    }
+
+    #[test] fn test_custom_return_type() {
+        let mut extended = Extended::new();
+
+        // Define a struct.
+        struct IntPlugin;
+
+        // Map it onto an `i32` value.
+        impl Key for IntPlugin { type Value = i32; }
+
+        // Define the plugin evaluation function.
+        impl Plugin<Extended> for IntPlugin {
+            type Error = Void;
+
+            fn eval(_: &mut Extended) -> Result<i32, Void> {
+                Ok(0i32)
+            }
+        }
+        assert_eq!(extended.get::<IntPlugin>().void_unwrap(), 0i32);
+    }
+
+    #[test] fn test_collect_as() {
+        use super::TraitPlugin;
+
+        trait Number { fn number(&self) -> i32; }
+
+        impl Number for One { fn number(&self) -> i32 { self.0 } }
+        impl Number for Two { fn number(&self) -> i32 { self.0 } }
+
+        impl TraitPlugin<dyn Number> for One {
+            fn upcast(value: &One) -> &(dyn Number + 'static) { value }
+        }
+
+        impl TraitPlugin<dyn Number> for Two {
+            fn upcast(value: &Two) -> &(dyn Number + 'static) { value }
+        }
+
+        let mut extended = Extended::new();
+        extended.register_as::<One, dyn Number>();
+        extended.register_as::<Two, dyn Number>();
+        extended.register_as::<One, dyn Number>();
+        assert!(extended.collect_as::<dyn Number>().is_empty());
+
+        extended.get::<One>().void_unwrap();
+        extended.get::<Two>().void_unwrap();
+        let numbers: Vec<i32> = extended.collect_as::<dyn Number>()
+            .iter().map(|n| n.number()).collect();
+        assert_eq!(numbers, vec![1, 2]);
+    }
+
+    #[test] fn test_iter_registered() {
+        use super::TraitPlugin;
+
+        trait Dump { fn dump(&self) -> String; }
+
+        impl Dump for One { fn dump(&self) -> String { format!("{:?}", self) } }
+        impl Dump for Two { fn dump(&self) -> String { format!("{:?}", self) } }
+
+        impl TraitPlugin<dyn Dump> for One {
+            fn upcast(value: &One) -> &(dyn Dump + 'static) { value }
+        }
+
+        impl TraitPlugin<dyn Dump> for Two {
+            fn upcast(value: &Two) -> &(dyn Dump + 'static) { value }
+        }
+
+        let mut extended = Extended::new();
+        extended.register_as::<One, dyn Dump>();
+        extended.register_as::<Two, dyn Dump>();
+        extended.get::<Two>().void_unwrap();
+
+        let dumped: Vec<_> = extended.iter_registered::<dyn Dump>()
+            .map(|(name, value)| (name, value.dump())).collect();
+        assert_eq!(dumped, vec![(std::any::type_name::<Two>(), "Two(2)".to_string())]);
+    }
+
+    #[test] fn test_compute_from() {
+        struct Request {
+            path: String,
+            hits: usize
+        }
+
+        // A projection borrowing only the path of a request.
+        struct Path<'a>(&'a str);
+
+        struct Depth;
+
+        impl Key for Depth { type Value = usize; }
+
+        impl<'a> Plugin<Path<'a>> for Depth {
+            type Error = Void;
+
+            fn eval(path: &mut Path<'a>) -> Result<usize, Void> {
+                Ok(path.0.split('/').filter(|s| !s.is_empty()).count())
+            }
+        }
+
+        let mut request = Request { path: "/a/b/c".to_string(), hits: 0 };
+        let hits = &mut request.hits;
+        let depth = compute_from::<Depth, _>(Path(&request.path)).void_unwrap();
+        *hits += depth;
+        assert_eq!(request.hits, 3);
+    }
+
+    #[test] fn test_get_pinned() {
+        use std::marker::PhantomPinned;
+        use std::ptr;
+
+        // Points at itself once pinned.
+        struct Node { this: *const Node, _pinned: PhantomPinned }
+
+        struct Pinned;
+        impl Key for Pinned { type Value = Node; }
+        impl Plugin<Extended> for Pinned {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<Node, Void> {
+                Ok(Node { this: ptr::null(), _pinned: PhantomPinned })
+            }
+        }
+        impl PinPlugin<Extended> for Pinned {}
+
+        let mut extended = Extended::new();
+        let node = extended.get_pinned_mut::<Pinned>().void_unwrap();
+        unsafe {
+            let node = node.get_unchecked_mut();
+            node.this = node;
+        }
+
+        let node = extended.get_pinned::<Pinned>().void_unwrap();
+        assert!(ptr::eq(node.this, &*node));
+        assert!(extended.peek::<Pinned>().is_none());
+
+        extended.invalidate::<Pinned>();
+        assert!(extended.get_pinned::<Pinned>().void_unwrap().this.is_null());
+    }
+
+    #[test] fn test_get_weak() {
+        use std::rc::Rc;
+
+        let mut extended = Extended::new();
+        let weak = extended.get_weak::<One>().void_unwrap();
+        assert!(Rc::ptr_eq(&weak.upgrade().unwrap(), &extended.get_rc::<One>().void_unwrap()));
+        assert_eq!(*weak.upgrade().unwrap(), One(1));
+
+        extended.invalidate::<One>();
+        assert!(weak.upgrade().is_none());
+        assert_eq!(*extended.get_rc::<One>().void_unwrap(), One(1));
+    }
+
+    #[test] fn test_seed() {
+        let mut extended = Extended::new();
+        seed!(extended, One => One(10), Two => Two(20));
+        assert_eq!(extended.get::<One>(), Ok(One(10)));
+        assert_eq!(extended.get::<Two>(), Ok(Two(20)));
+        assert_eq!(extended.get::<Three>(), Ok(Three(3)));
+    }
+
+    #[test] fn test_get_ref_infallible() {
+        use std::convert::Infallible;
+
+        struct Answer;
+
+        impl Key for Answer { type Value = i32; }
+
+        impl Plugin<Extended> for Answer {
+            type Error = Infallible;
+
+            fn eval(_: &mut Extended) -> Result<i32, Infallible> { Ok(42) }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(*extended.get_ref_infallible::<Answer>(), 42);
+    }
+
+    #[cfg(feature = "void")]
+    #[test] fn test_get_ref_void() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ref_void::<One>(), &One(1));
+    }
+
+    #[test] fn test_invalidate_all_except() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        extended.insert::<Two>(Two(20));
+        extended.get::<Four>().void_unwrap();
+
+        invalidate_all_except!(extended, Two, Three);
+        assert_eq!(extended.extensions().len(), 1);
+        assert_eq!(extended.extensions().get::<Two>(), Some(&Two(20)));
+
+        invalidate_all_except!(extended);
+        assert!(extended.extensions().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_get_timed() {
+        let mut extended = Extended::new();
+        let (value, elapsed) = extended.get_timed::<One>().void_unwrap();
+        assert_eq!(value, One(1));
+        assert!(elapsed.is_some());
+        assert_eq!(extended.get_timed::<One>().void_unwrap(), (One(1), None));
+    }
+
+    #[test] fn test_view() {
+        use super::ViewPlugin;
+
+        struct Named {
+            name: String,
+            map: TypeMap
+        }
+
+        impl Extensible for Named {
+            fn extensions(&self) -> &TypeMap { &self.map }
+            fn extensions_mut(&mut self) -> &mut TypeMap { &mut self.map }
+        }
+
+        impl Pluggable for Named {}
+
+        struct FirstName;
+
+        impl ViewPlugin<Named> for FirstName {
+            type View<'a> = &'a str;
+
+            fn view(named: &Named) -> &str {
+                named.name.split(' ').next().unwrap_or("")
+            }
+        }
+
+        let named = Named { name: "Ada Lovelace".to_string(), map: TypeMap::new() };
+        assert_eq!(named.view::<FirstName>(), "Ada");
+        assert!(named.extensions().is_empty());
+    }
+
+    #[test] fn test_compute_if_absent() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.compute_if_absent::<One>(), Ok(One(1)));
+        assert_eq!(extended.peek::<One>(), None);
+
+        extended.insert::<One>(One(10));
+        assert_eq!(extended.compute_if_absent::<One>(), Ok(One(10)));
+    }
+
+    #[test] fn test_populate_keyed() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::KeyedPlugin;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Greeting;
+        impl Key for Greeting { type Value = &'static str; }
+        impl KeyedPlugin<Extended> for Greeting {
+            type Arg = &'static str;
+            type Error = &'static str;
+            fn eval(_: &mut Extended, locale: &&'static str) -> Result<&'static str, &'static str> {
+                EVALS.fetch_add(1, Ordering::SeqCst);
+                match *locale {
+                    "en" => Ok("hello"),
+                    "fr" => Ok("bonjour"),
+                    _ => Err("unknown locale")
+                }
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.populate_keyed::<Greeting, _>(vec!["en", "en"]), Ok(()));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+
+        assert_eq!(extended.get_keyed_ref::<Greeting>("fr"), Ok(&"bonjour"));
+        assert_eq!(extended.populate_keyed::<Greeting, _>(vec!["en", "fr"]), Ok(()));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 2);
+
+        assert_eq!(extended.populate_keyed::<Greeting, _>(vec!["de", "es"]), Err("unknown locale"));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test] fn test_populate_keyed_parallel() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::{KeyedPlugin, PureKeyedPlugin};
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Greeting;
+        impl Key for Greeting { type Value = &'static str; }
+        impl KeyedPlugin<Extended> for Greeting {
+            type Arg = &'static str;
+            type Error = &'static str;
+            fn eval(_: &mut Extended, locale: &&'static str) -> Result<&'static str, &'static str> {
+                <Greeting as PureKeyedPlugin<Extended>>::eval_pure(locale)
+            }
+        }
+        impl PureKeyedPlugin<Extended> for Greeting {
+            fn eval_pure(locale: &&'static str) -> Result<&'static str, &'static str> {
+                EVALS.fetch_add(1, Ordering::SeqCst);
+                match *locale {
+                    "en" => Ok("hello"),
+                    "fr" => Ok("bonjour"),
+                    _ => Err("unknown locale")
+                }
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.populate_keyed::<Greeting, _>(vec!["en"]), Ok(()));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+
+        let keys = vec!["en", "de", "fr", "fr", "de"];
+        assert_eq!(extended.populate_keyed_parallel::<Greeting, _>(keys), Err("unknown locale"));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 3);
+        assert_eq!(extended.get_keyed_ref::<Greeting>("fr"), Ok(&"bonjour"));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test] fn test_get_with_aux() {
+        use super::DiagnosticPlugin;
+
+        struct Parsed;
+        impl Key for Parsed { type Value = i32; }
+        impl DiagnosticPlugin<Extended> for Parsed {
+            type Aux = Vec<&'static str>;
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<(i32, Vec<&'static str>), Void> {
+                Ok((1, vec!["trailing whitespace"]))
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_with_aux::<Parsed>(), Ok((1, Some(vec!["trailing whitespace"]))));
+        assert_eq!(extended.get_with_aux::<Parsed>(), Ok((1, None)));
+        assert_eq!(extended.peek::<Parsed>(), Some(&1));
+    }
+
+    #[test] fn test_compute_with_arg() {
+        use super::ArgPlugin;
+
+        struct Scaled;
+
+        impl Key for Scaled { type Value = i32; }
+
+        impl ArgPlugin<Extended> for Scaled {
+            type Arg = i32;
+            type Error = Void;
+
+            fn eval(ext: &mut Extended, factor: i32) -> Result<i32, Void> {
+                Ok(ext.get::<Two>()?.0 * factor)
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.compute_with_arg::<Scaled>(3), Ok(6));
+        assert_eq!(extended.compute_with_arg::<Scaled>(5), Ok(10));
+        assert_eq!(extended.peek::<Scaled>(), None);
+    }
+
+    #[test] fn test_ensure() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.ensure::<One>(), Ok(()));
+        assert_eq!(extended.extensions().get::<One>(), Some(&One(1)));
+
+        extended.insert::<One>(One(10));
+        assert_eq!(extended.ensure::<One>(), Ok(()));
+        assert_eq!(extended.extensions().get::<One>(), Some(&One(10)));
+    }
+
+    #[test] fn test_hashmap_store() {
+        use std::any::{Any, TypeId};
+        use std::collections::HashMap;
+        use super::ExtensibleStore;
+
+        struct Plain {
+            map: HashMap<TypeId, Box<dyn Any>>
+        }
+
+        impl ExtensibleStore for Plain {
+            type Store = HashMap<TypeId, Box<dyn Any>>;
+
+            fn store(&self) -> &Self::Store { &self.map }
+            fn store_mut(&mut self) -> &mut Self::Store { &mut self.map }
+        }
+
+        impl Pluggable for Plain {}
+
+        struct Counter;
+
+        impl Key for Counter { type Value = usize; }
+
+        impl Plugin<Plain> for Counter {
+            type Error = Void;
+
+            fn eval(plain: &mut Plain) -> Result<usize, Void> {
+                Ok(plain.map.len() + 1)
+            }
+        }
+
+        let mut plain = Plain { map: HashMap::new() };
+        assert_eq!(plain.get::<Counter>(), Ok(1));
+        assert_eq!(plain.get::<Counter>(), Ok(1));
+        assert_eq!(plain.invalidate::<Counter>(), Some(1));
+        assert_eq!(plain.insert::<Counter>(5), None);
+        assert_eq!(plain.get_ref::<Counter>(), Ok(&5));
+    }
+
+    impl DependentPlugin<Extended> for One {
+        fn dependencies() -> Vec<TypeId> { Vec::new() }
+    }
+
+    impl DependentPlugin<Extended> for Two {
+        fn dependencies() -> Vec<TypeId> { vec![TypeId::of::<One>()] }
+    }
+
+    impl DependentPlugin<Extended> for Three {
+        fn dependencies() -> Vec<TypeId> { vec![TypeId::of::<Two>()] }
+    }
+
+    impl PinPlugin<Extended> for Two {}
+
+    #[test] fn test_recompute_dependents() {
+        let mut extended = Extended::new();
+        extended.register_dependent::<Two>();
+        extended.register_dependent::<Three>();
+        extended.get::<One>().void_unwrap();
+        extended.get::<Two>().void_unwrap();
+        extended.get::<Three>().void_unwrap();
+        extended.get::<Four>().void_unwrap();
+        extended.get_pinned::<Two>().void_unwrap();
+
+        assert_eq!(extended.recompute_dependents::<One>(), 2);
+        assert!(extended.extensions().contains::<One>());
+        assert!(!extended.extensions().contains::<Two>());
+        assert!(!extended.extensions().contains::<super::PinnedValue<Two>>());
+        assert!(!extended.extensions().contains::<Three>());
+        assert!(extended.extensions().contains::<Four>());
+        assert_eq!(extended.recompute_dependents::<One>(), 0);
+    }
+
+    #[test] fn test_warm_up_sorted() {
+        use super::WarmUpError;
+
+        let mut extended = Extended::new();
+        extended.register_warm_up::<Three>();
+        extended.register_warm_up::<One>();
+        extended.register_warm_up::<Two>();
+        extended.register_warm_up::<Three>();
+
+        let order = vec![TypeId::of::<One>(), TypeId::of::<Two>(), TypeId::of::<Three>()];
+        assert_eq!(extended.warm_up_sorted().ok(), Some(order.clone()));
+        assert!(extended.extensions().contains::<Three>());
+        assert_eq!(extended.warm_up_sorted().ok(), Some(order));
+
+        struct Ping;
+        struct Pong;
+
+        impl Key for Ping { type Value = (); }
+        impl Key for Pong { type Value = (); }
+
+        impl Plugin<Extended> for Ping {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<(), Void> { Ok(()) }
+        }
+
+        impl Plugin<Extended> for Pong {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<(), Void> { Ok(()) }
+        }
+
+        impl DependentPlugin<Extended> for Ping {
+            fn dependencies() -> Vec<TypeId> { vec![TypeId::of::<Pong>()] }
+        }
+
+        impl DependentPlugin<Extended> for Pong {
+            fn dependencies() -> Vec<TypeId> { vec![TypeId::of::<Ping>()] }
+        }
+
+        let mut cyclic = Extended::new();
+        cyclic.register_warm_up::<Ping>();
+        cyclic.register_warm_up::<Pong>();
+        assert!(matches!(cyclic.warm_up_sorted(), Err(WarmUpError::Recursion)));
+        let err: Box<dyn std::error::Error> = Box::new(cyclic.warm_up_sorted().unwrap_err());
+        assert_eq!(err.to_string(), "plugins depend on each other in a cycle");
+        assert!(!cyclic.extensions().contains::<Ping>());
+    }
+
+    #[test] fn test_export() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        extended.insert::<Two>(Two(20));
+        extended.get::<Three>().void_unwrap();
+
+        let map = export!(extended, One, Two, Four);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get::<Two>(), Some(&Two(20)));
+
+        let mut other = Extended::new();
+        *other.extensions_mut() = map;
+        assert_eq!(other.get::<One>(), Ok(One(1)));
+        assert_eq!(other.get::<Two>(), Ok(Two(20)));
+        assert_eq!(extended.extensions().len(), 3);
+    }
+
+    #[test] fn test_all_and_any_cached() {
+        let mut extended = Extended::new();
+        assert!(!any_cached!(extended, One, Two));
+
+        extended.get::<One>().void_unwrap();
+        assert!(any_cached!(extended, One, Two));
+        assert!(!all_cached!(extended, One, Two));
+
+        extended.get::<Two>().void_unwrap();
+        assert!(all_cached!(extended, One, Two));
+    }
+
+    #[cfg(feature = "poison")]
+    #[test] fn test_poison() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::PoisonError;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Panicky;
+
+        impl Key for Panicky { type Value = i32; }
+
+        impl Plugin<Extended> for Panicky {
+            type Error = Void;
+
+            fn eval(_: &mut Extended) -> Result<i32, Void> {
+                if EVALS.fetch_add(1, Ordering::SeqCst) == 0 { panic!("first evaluation") }
+                Ok(2)
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ref_or_poison::<Panicky>(), Err(PoisonError::Poisoned));
+        assert_eq!(extended.get_ref_or_poison::<Panicky>(), Err(PoisonError::Poisoned));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+
+        assert!(extended.clear_poison::<Panicky>());
+        assert_eq!(extended.get_ref_or_poison::<Panicky>(), Ok(&2));
+        assert!(!extended.clear_poison::<Panicky>());
+    }
+
+    #[cfg(feature = "poison")]
+    #[test] fn test_poison_get() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::PoisonError;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Panicky;
+
+        impl Key for Panicky { type Value = i32; }
+
+        impl Plugin<Extended> for Panicky {
+            type Error = Void;
+
+            fn eval(_: &mut Extended) -> Result<i32, Void> {
+                if EVALS.fetch_add(1, Ordering::SeqCst) == 0 { panic!("first evaluation") }
+                Ok(2)
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| extended.get::<Panicky>())).is_err());
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| extended.get::<Panicky>())).is_err());
+        assert_eq!(extended.get_ref_or_poison::<Panicky>(), Err(PoisonError::Poisoned));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+
+        assert!(extended.clear_poison::<Panicky>());
+        assert_eq!(extended.get::<Panicky>(), Ok(2));
+    }
+
+    #[test] fn test_get_ref_many() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.peek::<One>(), None);
+
+        let (one, two, three) = get_ref_many!(extended, One, Two, Three).void_unwrap();
+        assert_eq!((one, two, three), (&One(1), &Two(2), &Three(3)));
+        assert_eq!(extended.peek::<Two>(), Some(&Two(2)));
+    }
+
+    #[test] fn test_plugin_ext() {
+        use super::PluginExt;
+
+        struct Failing;
+
+        impl Key for Failing { type Value = i32; }
+
+        impl Plugin<Extended> for Failing {
+            type Error = ();
+
+            fn eval(_: &mut Extended) -> Result<i32, ()> { Err(()) }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_opt::<One>(), Some(One(1)));
+        assert_eq!(extended.get_opt::<Failing>(), None);
+        assert_eq!(extended.get_mapped::<Failing, _, _>(|()| "failed"), Err("failed"));
+        assert_eq!(extended.get_or_default::<Failing>(), 0);
+        assert_eq!(extended.peek::<Failing>(), None);
+    }
+
+    #[test] fn test_with_plugin_and_field() {
+        struct Counted {
+            count: i32,
+            map: TypeMap
+        }
+
+        impl Extensible for Counted {
+            fn extensions(&self) -> &TypeMap { &self.map }
+            fn extensions_mut(&mut self) -> &mut TypeMap { &mut self.map }
+        }
+
+        impl Pluggable for Counted {}
+
+        struct Step;
+
+        impl Key for Step { type Value = i32; }
+
+        impl Plugin<Counted> for Step {
+            type Error = Void;
+
+            fn eval(_: &mut Counted) -> Result<i32, Void> { Ok(5) }
+        }
+
+        let mut counted = Counted { count: 1, map: TypeMap::new() };
+        let sum = with_plugin_and_field!(counted, Step, count, |step: &mut i32, count: &mut i32| {
+            *count += *step;
+            *step += 1;
+            *count
+        });
+        assert_eq!(sum.void_unwrap(), 6);
+        assert_eq!(counted.count, 6);
+        assert_eq!(counted.peek::<Step>(), Some(&6));
+    }
+
+    #[test] fn test_evictable() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::EvictablePlugin;
+
+        static EVICTED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Connection;
+
+        impl Key for Connection { type Value = i32; }
+
+        impl Plugin<Extended> for Connection {
+            type Error = Void;
+
+            fn eval(_: &mut Extended) -> Result<i32, Void> { Ok(1) }
+        }
+
+        impl EvictablePlugin for Connection {
+            fn on_evict(value: &mut i32) {
+                EVICTED.fetch_add(1, Ordering::SeqCst);
+                *value = -1;
+            }
+        }
+
+        let mut extended = Extended::new();
+        extended.register_evictable::<Connection>();
+        extended.get::<Connection>().void_unwrap();
+
+        assert_eq!(extended.insert::<Connection>(2), Some(-1));
+        assert_eq!(extended.take::<Connection>(), Some(-1));
+        assert_eq!(extended.invalidate::<Connection>(), None);
+        assert_eq!(EVICTED.load(Ordering::SeqCst), 2);
+
+        extended.get::<Connection>().void_unwrap();
+        extended.clear_extensions();
+        assert!(extended.extensions().is_empty());
+        assert_eq!(EVICTED.load(Ordering::SeqCst), 3);
+
+        extended.get::<Connection>().void_unwrap();
+        assert_eq!(extended.invalidate::<Connection>(), Some(1));
+        assert_eq!(EVICTED.load(Ordering::SeqCst), 3);
+    }
+
+    #[test] fn test_register_alias() {
+        struct Legacy;
+        impl Key for Legacy { type Value = i32; }
+        impl Plugin<Extended> for Legacy {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> { Ok(1) }
+        }
+
+        struct Current;
+        impl Key for Current { type Value = i32; }
+
+        let mut extended = Extended::new();
+        extended.register_alias::<Legacy, Current>();
+        extended.insert::<Current>(5);
+        assert_eq!(extended.get::<Legacy>(), Ok(5));
+        assert_eq!(extended.peek::<Legacy>(), None);
+
+        extended.invalidate::<Current>();
+        assert_eq!(extended.get::<Legacy>(), Ok(1));
+        assert_eq!(extended.peek::<Current>(), Some(&1));
+        assert_eq!(extended.peek::<Legacy>(), None);
+        assert_eq!(extended.get_ref_versioned::<Legacy>(), Ok((&1, 0)));
+
+        extended.insert::<Current>(7);
+        assert_eq!(extended.compute_if_absent::<Legacy>(), Ok(7));
+        assert_eq!(extended.get_with_fuel::<Legacy>(0).ok(), Some(7));
+
+        extended.invalidate::<Current>();
+        assert_eq!(extended.get_cache_if::<Legacy, _>(|_| true), Ok(1));
+        assert_eq!(extended.peek::<Current>(), Some(&1));
+        assert_eq!(extended.peek::<Legacy>(), None);
+
+        assert_eq!(extended.insert::<Legacy>(8), Some(1));
+        assert_eq!(extended.peek::<Current>(), Some(&8));
+        assert_eq!(extended.get_validated::<Legacy, _>(|&value| value != 8), Ok(1));
+        assert_eq!(extended.invalidate::<Legacy>(), Some(1));
+        assert_eq!(extended.peek::<Current>(), None);
+        assert_eq!(extended.take::<Legacy>(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_register_alias_ttl() {
+        use std::time::Duration;
+        use super::TtlPlugin;
+
+        struct Legacy;
+        impl Key for Legacy { type Value = i32; }
+        impl Plugin<Extended> for Legacy {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> { Ok(1) }
+        }
+        impl TtlPlugin<Extended> for Legacy {
+            fn ttl() -> Duration { Duration::from_secs(0) }
+        }
+
+        struct Current;
+        impl Key for Current { type Value = i32; }
+
+        let mut extended = Extended::new();
+        extended.register_alias::<Legacy, Current>();
+        assert_eq!(extended.get_ttl::<Legacy>(), Ok(1));
+
+        extended.insert::<Current>(7);
+        assert_eq!(extended.get_ttl::<Legacy>(), Ok(1));
+        assert_eq!(extended.peek::<Current>(), Some(&1));
+    }
+
+    #[test] fn test_get_result() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Missing;
+
+        impl Key for Missing { type Value = i32; }
+
+        impl Plugin<Extended> for Missing {
+            type Error = &'static str;
+
+            fn eval(_: &mut Extended) -> Result<i32, &'static str> {
+                EVALS.fetch_add(1, Ordering::SeqCst);
+                Err("missing")
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_result::<Missing>(), &Err("missing"));
+        assert_eq!(extended.get_result::<Missing>(), &Err("missing"));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+
+        assert_eq!(extended.clear_result::<Missing>(), Some(Err("missing")));
+        assert_eq!(extended.get_result::<Missing>(), &Err("missing"));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 2);
+        assert_eq!(extended.get_result::<One>(), &Ok(One(1)));
+    }
+
+    #[test] fn test_get_value_typed() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_value_typed::<One>(), None);
+
+        extended.get::<One>().void_unwrap();
+        extended.insert::<IntKey>(5);
+        assert_eq!(extended.get_value_typed::<One>(), Some(&One(1)));
+        assert!(extended.get_value_typed::<IntKey>().is_none());
+    }
+
+    #[test] fn test_compute_into_store() {
+        let mut extended = Extended::new();
+        let mut store = TypeMap::new();
+        assert_eq!(extended.compute_into_store::<One, _>(&mut store), Ok(()));
+        assert_eq!(store.get::<One>(), Some(&One(1)));
+        assert!(extended.extensions().is_empty());
+    }
+
+    #[test] fn test_get_chained() {
+        use super::Chained;
+
+        struct Scope<'p> {
+            parent: Option<&'p Scope<'p>>,
+            map: TypeMap
+        }
+
+        impl<'p> Extensible for Scope<'p> {
+            fn extensions(&self) -> &TypeMap { &self.map }
+            fn extensions_mut(&mut self) -> &mut TypeMap { &mut self.map }
+        }
+
+        impl<'p> Chained for Scope<'p> {
+            fn parent(&self) -> Option<&dyn Chained> {
+                self.parent.map(|parent| parent as &dyn Chained)
+            }
+        }
+
+        impl<'p> Pluggable for Scope<'p> {}
+
+        struct Level;
+
+        impl Key for Level { type Value = usize; }
+
+        impl<'p> Plugin<Scope<'p>> for Level {
+            type Error = Void;
+
+            fn eval(scope: &mut Scope<'p>) -> Result<usize, Void> {
+                Ok(if scope.parent.is_some() { 1 } else { 0 })
+            }
+        }
+
+        let mut root = Scope { parent: None, map: TypeMap::new() };
+        root.insert::<IntKey>(7);
+        let middle = Scope { parent: Some(&root), map: TypeMap::new() };
+        let mut leaf = Scope { parent: Some(&middle), map: TypeMap::new() };
+
+        assert_eq!(leaf.get_chained::<Level>(), Ok(&1));
+        assert_eq!(leaf.peek::<Level>(), Some(&1));
+        assert_eq!(middle.peek::<Level>(), None);
+
+        struct Missing;
+
+        impl Key for Missing { type Value = i32; }
+
+        impl<'p> Plugin<Scope<'p>> for Missing {
+            type Error = ();
+
+            fn eval(_: &mut Scope<'p>) -> Result<i32, ()> { Err(()) }
+        }
+
+        impl<'p> Plugin<Scope<'p>> for IntKey {
+            type Error = ();
+
+            fn eval(_: &mut Scope<'p>) -> Result<i32, ()> { Err(()) }
+        }
+
+        assert_eq!(leaf.get_chained::<IntKey>(), Ok(&7));
+        assert_eq!(leaf.get_chained::<Missing>(), Err(()));
+    }
+
+    #[test] fn test_get_results() {
+        struct Failing;
+
+        impl Key for Failing { type Value = i32; }
+
+        impl Plugin<Extended> for Failing {
+            type Error = &'static str;
+
+            fn eval(_: &mut Extended) -> Result<i32, &'static str> { Err("failed") }
+        }
+
+        let mut extended = Extended::new();
+        let (one, failing, two) = get_results!(extended, One, Failing, Two);
+        assert_eq!(one, Ok(One(1)));
+        assert_eq!(failing, Err("failed"));
+        assert_eq!(two, Ok(Two(2)));
+    }
+
+    #[test] fn test_with_extensions() {
+        let mut map = TypeMap::new();
+        map.insert::<One>(One(10));
+
+        let mut extended = Extended::new().with_extensions(map);
+        assert_eq!(extended.get::<One>(), Ok(One(10)));
+    }
+
+    #[test] fn test_with_overrides() {
+        let mut extended = Extended::new();
+        extended.insert::<One>(One(1));
+
+        let seen = with_overrides!(extended, [One => One(10), Two => Two(20)], |ctx: &mut Extended| {
+            (ctx.get::<One>(), ctx.get::<Two>())
+        });
+        assert_eq!(seen, (Ok(One(10)), Ok(Two(20))));
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+        assert_eq!(extended.peek::<Two>(), None);
+
+        let panicked = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            with_overrides!(extended, [One => One(10)], |_: &mut Extended| panic!("overridden"))
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+    }
+
+    #[test] fn test_get_cache_if() {
+        let mut extended = Extended::new();
+
+        assert_eq!(extended.get_cache_if::<One, _>(|_| false), Ok(One(1)));
+        assert_eq!(extended.peek::<One>(), None);
+
+        assert_eq!(extended.get_cache_if::<One, _>(|&One(n)| n > 0), Ok(One(1)));
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+    }
+
+    #[test] fn test_extension_report() {
+        let mut extended = Extended::new();
+        assert!(extended.extension_report().is_empty());
+
+        extended.register_for_report::<One>();
+        extended.register_for_report::<Two>();
+        extended.register_for_report::<One>();
+        extended.get::<Two>().void_unwrap();
+
+        let report = extended.extension_report();
+        assert_eq!(report.len(), 2);
+        assert!(report[0].0.ends_with("One") && !report[0].1);
+        assert!(report[1].0.ends_with("Two") && report[1].1);
+    }
+
+    #[test] fn test_compute_named() {
+        struct Failing;
+        impl Key for Failing { type Value = i32; }
+        impl Plugin<Extended> for Failing {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<i32, &'static str> { Err("failed") }
+        }
+
+        let mut extended = Extended::new();
+        extended.register_named::<One>("one");
+        extended.register_named::<Failing>("failing");
+        assert!(extended.compute_named("two").is_none());
+
+        let one = extended.compute_named("one").unwrap().unwrap();
+        assert_eq!(one.downcast_ref::<One>(), Some(&One(1)));
+        assert_eq!(extended.peek::<One>(), None);
+
+        let err = extended.compute_named("failing").unwrap().unwrap_err();
+        assert_eq!(err.downcast_ref::<&'static str>(), Some(&"failed"));
+    }
+
+    #[test] fn test_recompute_all() {
+        use std::cell::Cell;
+
+        thread_local!(static SOURCE: Cell<i32> = const { Cell::new(1) });
+
+        struct Configured;
+        impl Key for Configured { type Value = i32; }
+        impl Plugin<Extended> for Configured {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<i32, &'static str> {
+                match SOURCE.with(Cell::get) {
+                    0 => Err("unconfigured"),
+                    n => Ok(n)
+                }
+            }
+        }
+
+        impl PinPlugin<Extended> for Configured {}
+
+        let mut extended = Extended::new();
+        extended.register_recompute::<Configured>();
+        extended.register_recompute::<One>();
+        assert!(extended.recompute_all().is_empty());
+        assert_eq!(extended.peek::<Configured>(), None);
+
+        extended.get::<Configured>().unwrap();
+        assert_eq!(*extended.get_pinned::<Configured>().unwrap(), 1);
+        SOURCE.with(|source| source.set(2));
+        assert!(extended.recompute_all().is_empty());
+        assert_eq!(extended.peek::<Configured>(), Some(&2));
+        assert_eq!(*extended.get_pinned::<Configured>().unwrap(), 2);
+        assert_eq!(extended.peek::<One>(), None);
+
+        SOURCE.with(|source| source.set(0));
+        let errors = extended.recompute_all();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, TypeId::of::<Configured>());
+        assert_eq!(errors[0].1.downcast_ref::<&'static str>(), Some(&"unconfigured"));
+        assert_eq!(extended.peek::<Configured>(), Some(&2));
+    }
+
+    #[cfg(feature = "async")]
+    #[test] fn test_get_blocking() {
+        use std::future::{self, Future};
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+        use super::AsyncPlugin;
+
+        // Pending once, waking itself, before completing.
+        struct YieldOnce(bool);
+
+        impl Future for YieldOnce {
+            type Output = Result<i32, ()>;
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<i32, ()>> {
+                if self.0 { return Poll::Ready(Ok(5)) }
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+
+        struct Slow;
+        impl Key for Slow { type Value = i32; }
+        impl AsyncPlugin<Extended> for Slow {
+            type Error = ();
+            type Future<'a> = YieldOnce;
+            fn eval(_: &mut Extended) -> YieldOnce { YieldOnce(false) }
+        }
+
+        struct Ready;
+        impl Key for Ready { type Value = i32; }
+        impl AsyncPlugin<Extended> for Ready {
+            type Error = &'static str;
+            type Future<'a> = future::Ready<Result<i32, &'static str>>;
+            fn eval(_: &mut Extended) -> Self::Future<'_> { future::ready(Err("failed")) }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_blocking::<Slow>(), Ok(5));
+        assert_eq!(extended.peek::<Slow>(), Some(&5));
+        assert_eq!(extended.get_blocking::<Ready>(), Err("failed"));
+    }
+
+    #[test] fn test_assert_cached() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        extended.assert_cached::<One>();
+    }
+
+    #[cfg(debug_assertions)]
+    #[test] #[should_panic(expected = "is not cached")]
+    fn test_assert_cached_missing() {
+        Extended::new().assert_cached::<One>();
+    }
+
+    #[test] fn test_get_ref_or_insert() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ref_or_insert::<One>(One(10)), &One(10));
+        assert_eq!(extended.get_ref_or_insert::<One>(One(20)), &One(10));
+        assert_eq!(extended.get::<One>(), Ok(One(10)));
+    }
+
+    #[test] fn test_get_ref_or_try_init() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ref_or_try_init::<IntKey, _, _>(|| Err("failed")), Err("failed"));
+        assert_eq!(extended.peek::<IntKey>(), None);
+
+        assert_eq!(extended.get_ref_or_try_init::<IntKey, (), _>(|| Ok(1)), Ok(&1));
+        assert_eq!(extended.get_ref_or_try_init::<IntKey, (), _>(|| Ok(2)), Ok(&1));
+    }
+
+    #[test] fn test_accumulate() {
+        let mut extended = Extended::new();
+        *extended.accumulate::<IntKey>() += 2;
+        *extended.accumulate::<IntKey>() += 3;
+        assert_eq!(extended.peek::<IntKey>(), Some(&5));
+    }
+
+    #[test] fn test_lazy() {
+        let mut extended = Extended::new();
+        let one = Extended::lazy::<One>();
+        assert_eq!(extended.get::<Two>(), Ok(Two(2)));
+        extended.invalidate::<One>();
+        assert_eq!(extended.peek::<One>(), None);
+
+        assert_eq!(one(&mut extended), Ok(One(1)));
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+    }
+
+    #[test] fn test_get_from_map() {
+        use super::get_from_map;
+
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        let snapshot = export!(extended, One, Two);
+
+        assert_eq!(get_from_map::<One>(&snapshot), Some(&One(1)));
+        assert_eq!(get_from_map::<Two>(&snapshot), None);
+    }
+
+    #[test] fn test_diff_type_ids() {
+        use super::diff_type_ids;
+
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        let before = export!(extended, One, Two, Three);
+
+        extended.invalidate::<One>();
+        extended.get::<Two>().void_unwrap();
+        let after = export!(extended, One, Two, Three);
+
+        assert_eq!(diff_type_ids(&before, &after),
+                   (vec![TypeId::of::<Two>()], vec![TypeId::of::<One>()]));
+        assert_eq!(diff_type_ids(&after, &after), (vec![], vec![]));
+    }
+
+    #[test] fn test_diff_values() {
+        use super::{diff_values, ComparablePlugin};
+
+        struct Count;
+        impl Key for Count { type Value = i32; }
+        impl ComparablePlugin for Count {}
+
+        struct Label;
+        impl Key for Label { type Value = &'static str; }
+        impl ComparablePlugin for Label {}
+
+        let mut extended = Extended::new();
+        extended.register_comparable::<Count>();
+        extended.register_comparable::<Label>();
+        extended.insert::<Count>(1);
+        extended.insert::<Label>("label");
+        extended.get::<One>().void_unwrap();
+        let before = export!(extended, Count, Label, One);
+
+        extended.insert::<Count>(2);
+        extended.insert::<One>(One(10));
+        assert_eq!(diff_values(&before, extended.extensions()), vec![std::any::type_name::<Count>()]);
+        assert!(diff_values(&before, &before).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_get_by_deadline() {
+        use std::time::{Duration, Instant};
+        use super::DeadlineError;
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_by_deadline::<One>(), Ok(One(1)));
+
+        let now = Instant::now();
+        assert_eq!(extended.set_deadline(now + Duration::from_secs(60)), None);
+        assert_eq!(extended.get_by_deadline::<Two>(), Ok(Two(2)));
+
+        extended.set_deadline(now);
+        assert_eq!(extended.deadline(), Some(now));
+        assert_eq!(extended.get_by_deadline::<One>(), Ok(One(1)));
+        assert_eq!(extended.get_by_deadline::<Three>(), Err(DeadlineError::Exceeded));
+        assert_eq!(extended.peek::<Three>(), None);
+    }
+
+    #[test] fn test_get_arc() {
+        use std::sync::{Arc, RwLock};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::thread;
+        use std::time::Duration;
+        use typemap::ShareMap;
+        use super::{SyncExtensible, SyncPluggable};
+
+        static EVALUATIONS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone)]
+        struct Shared { map: Arc<RwLock<ShareMap>> }
+
+        impl SyncExtensible for Shared {
+            fn shared_extensions(&self) -> &Arc<RwLock<ShareMap>> { &self.map }
+        }
+
+        impl SyncPluggable for Shared {}
+
+        struct Slow;
+        impl Key for Slow { type Value = usize; }
+        impl Plugin<Shared> for Slow {
+            type Error = Void;
+            fn eval(_: &mut Shared) -> Result<usize, Void> {
+                thread::sleep(Duration::from_millis(20));
+                Ok(EVALUATIONS.fetch_add(1, Ordering::SeqCst))
+            }
+        }
+
+        let shared = Shared { map: Arc::new(RwLock::new(ShareMap::custom())) };
+        let racers: Vec<_> = (0..4).map(|_| {
+            let mut shared = shared.clone();
+            thread::spawn(move || shared.get_arc::<Slow>().void_unwrap())
+        }).collect();
+        let values: Vec<Arc<usize>> = racers.into_iter().map(|racer| racer.join().unwrap()).collect();
+
+        assert_eq!(EVALUATIONS.load(Ordering::SeqCst), 1);
+        assert!(values.iter().all(|value| Arc::ptr_eq(value, &values[0])));
+        assert!(Arc::ptr_eq(&shared.clone().get_arc::<Slow>().void_unwrap(), &values[0]));
+
+        // Cache hits only take the read lock, so they proceed while other
+        // readers hold it.
+        let _reader = shared.map.read().unwrap();
+        let readers: Vec<_> = (0..4).map(|_| {
+            let mut shared = shared.clone();
+            thread::spawn(move || shared.get_arc::<Slow>().void_unwrap())
+        }).collect();
+        assert!(readers.into_iter().all(|reader| Arc::ptr_eq(&reader.join().unwrap(), &values[0])));
+    }
+
+    #[test] fn test_project() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.project::<Two, _, _>(|&Two(n)| n * 10), Ok(20));
+        assert_eq!(extended.project::<Two, _, _>(|&Two(n)| n + 1), Ok(3));
+        assert_eq!(extended.peek::<Two>(), Some(&Two(2)));
+    }
+
+    #[test] fn test_get_validated() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_validated::<One, _>(|_| false), Ok(One(1)));
+
+        extended.insert::<One>(One(-1));
+        assert_eq!(extended.get_validated::<One, _>(|&One(n)| n < 0), Ok(One(-1)));
+        assert_eq!(extended.get_validated::<One, _>(|&One(n)| n > 0), Ok(One(1)));
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+    }
+
+    #[test] fn test_iter_group() {
+        struct Index;
+        impl Key for Index { type Value = &'static str; }
+
+        struct About;
+        impl Key for About { type Value = &'static str; }
+
+        let mut extended = Extended::new();
+        extended.register_in_group::<Index>("routes");
+        extended.register_in_group::<About>("routes");
+        extended.register_in_group::<One>("routes");
+        extended.register_in_group::<Index>("routes");
+        assert_eq!(extended.iter_group::<&'static str>("routes").count(), 0);
+
+        extended.insert::<Index>("/");
+        extended.insert::<About>("/about");
+        extended.get::<One>().void_unwrap();
+
+        let routes: Vec<_> = extended.iter_group::<&'static str>("routes").collect();
+        assert_eq!(routes, vec![&"/", &"/about"]);
+        assert_eq!(extended.iter_group::<&'static str>("other").count(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_sweep_expired() {
+        use std::time::{Duration, Instant};
+        use super::TtlPlugin;
+
+        struct Short;
+        impl Key for Short { type Value = i32; }
+        impl Plugin<Extended> for Short {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> { Ok(1) }
+        }
+        impl TtlPlugin<Extended> for Short {
+            fn ttl() -> Duration { Duration::from_secs(1) }
+        }
+        impl PinPlugin<Extended> for Short {}
+
+        struct Long;
+        impl Key for Long { type Value = i32; }
+        impl Plugin<Extended> for Long {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> { Ok(2) }
+        }
+        impl TtlPlugin<Extended> for Long {
+            fn ttl() -> Duration { Duration::from_secs(3600) }
+        }
+
+        struct Forever;
+        impl Key for Forever { type Value = i32; }
+        impl Plugin<Extended> for Forever {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> { Ok(3) }
+        }
+        impl TtlPlugin<Extended> for Forever {
+            fn ttl() -> Duration { Duration::MAX }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ttl::<Short>(), Ok(1));
+        assert_eq!(extended.get_ttl::<Long>(), Ok(2));
+        assert_eq!(extended.get_ttl::<Forever>(), Ok(3));
+        assert_eq!(extended.get_ttl::<Forever>(), Ok(3));
+        extended.get_pinned::<Short>().void_unwrap();
+        extended.get::<One>().void_unwrap();
+
+        let now = Instant::now();
+        assert_eq!(extended.sweep_expired(now), 0);
+        assert_eq!(extended.sweep_expired(now + Duration::from_secs(60)), 1);
+        assert_eq!(extended.peek::<Short>(), None);
+        assert!(!extended.extensions().contains::<super::PinnedValue<Short>>());
+        assert_eq!(extended.peek::<Long>(), Some(&2));
+        assert_eq!(extended.peek::<Forever>(), Some(&3));
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+
+        assert_eq!(extended.sweep_expired(now + Duration::from_secs(60)), 0);
+        assert_eq!(extended.get_ttl::<Short>(), Ok(1));
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_get_global() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use super::GlobalPlugin;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Compiled;
+        impl Key for Compiled { type Value = String; }
+        impl Plugin<Extended> for Compiled {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<String, Void> {
+                EVALS.fetch_add(1, Ordering::SeqCst);
+                Ok("compiled".to_string())
+            }
+        }
+        impl GlobalPlugin<Extended> for Compiled {}
+
+        let first = Extended::new().get_global::<Compiled>().void_unwrap();
+        let second = Extended::new().get_global::<Compiled>().void_unwrap();
+        assert_eq!(*first, "compiled");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_get_global_racing() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::{Arc, Barrier};
+        use std::thread;
+        use std::time::Duration;
+        use super::GlobalPlugin;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Slow;
+        impl Key for Slow { type Value = usize; }
+        impl Plugin<Extended> for Slow {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<usize, Void> {
+                thread::sleep(Duration::from_millis(20));
+                Ok(EVALS.fetch_add(1, Ordering::SeqCst))
+            }
+        }
+        impl GlobalPlugin<Extended> for Slow {}
+
+        let barrier = Arc::new(Barrier::new(8));
+        let threads: Vec<_> = (0..8).map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                *Extended::new().get_global::<Slow>().void_unwrap()
+            })
+        }).collect();
+
+        for thread in threads { assert_eq!(thread.join().unwrap(), 0); }
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_get_ttl_graceful() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::Duration;
+
+        static UP: AtomicBool = AtomicBool::new(true);
+
+        struct Upstream;
+        impl Key for Upstream { type Value = i32; }
+        impl Plugin<Extended> for Upstream {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<i32, &'static str> {
+                if UP.load(Ordering::SeqCst) { Ok(1) } else { Err("down") }
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ttl_graceful::<Upstream>(Duration::from_secs(0)), Ok(1));
+
+        UP.store(false, Ordering::SeqCst);
+        extended.insert::<Upstream>(2);
+        assert_eq!(extended.get_ttl_graceful::<Upstream>(Duration::from_secs(0)), Ok(2));
+        assert_eq!(extended.peek::<Upstream>(), Some(&2));
+
+        UP.store(true, Ordering::SeqCst);
+        assert_eq!(extended.get_ttl_graceful::<Upstream>(Duration::from_secs(0)), Ok(1));
+
+        UP.store(false, Ordering::SeqCst);
+        assert_eq!(extended.get_ttl_graceful::<Upstream>(Duration::MAX), Ok(1));
+
+        extended.invalidate::<Upstream>();
+        assert_eq!(extended.get_ttl_graceful::<Upstream>(Duration::from_secs(0)), Err("down"));
+
+        UP.store(true, Ordering::SeqCst);
+        assert_eq!(extended.get_ttl_graceful::<Upstream>(Duration::MAX), Ok(1));
+        extended.get_shared::<Upstream>().unwrap();
+        UP.store(false, Ordering::SeqCst);
+        assert_eq!(extended.get_ttl_graceful::<Upstream>(Duration::from_secs(0)), Ok(1));
+    }
+
+    #[test] fn test_get_ref_unchecked() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        extended.assert_cached::<One>();
+        assert_eq!(unsafe { extended.get_ref_unchecked::<One>() }, &One(1));
+    }
+
+    #[test] fn test_get_or_record() {
+        use std::any::Any;
+        use super::ErrorSink;
+
+        struct Handler { map: TypeMap, errors: Vec<Box<dyn Any>> }
+
+        impl Extensible for Handler {
+            fn extensions(&self) -> &TypeMap { &self.map }
+            fn extensions_mut(&mut self) -> &mut TypeMap { &mut self.map }
+        }
+
+        impl ErrorSink for Handler {
+            fn record_error(&mut self, err: Box<dyn Any>) { self.errors.push(err) }
+        }
+
+        impl Pluggable for Handler {}
+
+        struct Failing;
+        impl Key for Failing { type Value = i32; }
+        impl Plugin<Handler> for Failing {
+            type Error = &'static str;
+            fn eval(_: &mut Handler) -> Result<i32, &'static str> { Err("failed") }
+        }
+
+        struct Working;
+        impl Key for Working { type Value = i32; }
+        impl Plugin<Handler> for Working {
+            type Error = &'static str;
+            fn eval(_: &mut Handler) -> Result<i32, &'static str> { Ok(1) }
+        }
+
+        let mut handler = Handler { map: TypeMap::new(), errors: Vec::new() };
+        assert_eq!(handler.get_or_record::<Failing>(), None);
+        assert_eq!(handler.get_or_record::<Working>(), Some(1));
+        assert_eq!(handler.get_or_record::<Failing>(), None);
+
+        assert_eq!(handler.errors.len(), 2);
+        assert_eq!(handler.errors[0].downcast_ref::<&'static str>(), Some(&"failed"));
+    }
+
+    #[cfg(feature = "thread")]
+    #[test] fn test_warm_up_scoped() {
+        use std::sync::{Arc, RwLock};
+        use typemap::ShareMap;
+        use super::{PluginRef, SyncExtensible, SyncPluggable};
+
+        struct Shared { map: Arc<RwLock<ShareMap>>, base: i32 }
+
+        impl SyncExtensible for Shared {
+            fn shared_extensions(&self) -> &Arc<RwLock<ShareMap>> { &self.map }
+        }
+
+        impl SyncPluggable for Shared {}
+
+        struct Double;
+        impl Key for Double { type Value = i32; }
+        impl PluginRef<Shared> for Double {
+            type Error = ();
+            fn eval_ref(shared: &Shared) -> Result<i32, ()> { Ok(shared.base * 2) }
+        }
+        impl Plugin<Shared> for Double {
+            type Error = ();
+            fn eval(shared: &mut Shared) -> Result<i32, ()> { Double::eval_ref(shared) }
+        }
+
+        struct Failing;
+        impl Key for Failing { type Value = i32; }
+        impl PluginRef<Shared> for Failing {
+            type Error = ();
+            fn eval_ref(_: &Shared) -> Result<i32, ()> { Err(()) }
+        }
+        impl Plugin<Shared> for Failing {
+            type Error = ();
+            fn eval(shared: &mut Shared) -> Result<i32, ()> { Failing::eval_ref(shared) }
+        }
+
+        let mut shared = Shared { map: Arc::new(RwLock::new(ShareMap::custom())), base: 3 };
+        warm_up_scoped!(shared, Double, Failing);
+        shared.base = 0;
+
+        assert_eq!(shared.get_arc::<Double>().map(|value| *value), Ok(6));
+        assert_eq!(shared.get_arc::<Failing>(), Err(()));
+    }
+
+    #[test] fn test_get_ref_or() {
+        struct Broken;
+        impl Key for Broken { type Value = &'static str; }
+        impl Plugin<Extended> for Broken {
+            type Error = ();
+            fn eval(_: &mut Extended) -> Result<&'static str, ()> { Err(()) }
+        }
+
+        let default = One(10);
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ref_or::<One>(&default), &One(1));
+        assert_eq!(extended.get_ref_or::<Broken>(&"default"), &"default");
+        assert_eq!(extended.peek::<Broken>(), None);
+    }
+
+    #[test] fn test_guarded_eval() {
+        use super::EvalError;
+
+        struct Recursive;
+        impl Key for Recursive { type Value = i32; }
+        impl Plugin<Extended> for Recursive {
+            type Error = &'static str;
+            fn eval(ext: &mut Extended) -> Result<i32, &'static str> {
+                match ext.guarded_eval::<Recursive>() {
+                    Err(EvalError::Recursion) => Err("recursion"),
+                    _ => Ok(0)
+                }
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.guarded_eval::<Recursive>(), Err(EvalError::Plugin("recursion")));
+        assert_eq!(extended.guarded_eval::<Recursive>(), Err(EvalError::Plugin("recursion")));
+        assert_eq!(extended.guarded_eval::<One>(), Ok(One(1)));
+        assert_eq!(extended.peek::<One>(), None);
+    }
+
+    #[test] fn test_send_pluggable() {
+        use std::thread;
+        use typemap::SendMap;
+        use super::{SendExtensible, SendPluggable};
+
+        struct Job { map: SendMap, input: i32 }
+
+        impl SendExtensible for Job {
+            fn extensions(&self) -> &SendMap { &self.map }
+            fn extensions_mut(&mut self) -> &mut SendMap { &mut self.map }
+        }
+
+        impl SendPluggable for Job {}
+
+        struct Squared;
+        impl Key for Squared { type Value = i32; }
+        impl Plugin<Job> for Squared {
+            type Error = Void;
+            fn eval(job: &mut Job) -> Result<i32, Void> { Ok(job.input * job.input) }
+        }
+
+        let mut job = Job { map: SendMap::custom(), input: 3 };
+        assert_eq!(job.get::<Squared>(), Ok(9));
+
+        let mut job = thread::spawn(move || { job.input = 4; job }).join().unwrap();
+        assert_eq!(job.get_ref::<Squared>(), Ok(&9));
+        *job.get_mut::<Squared>().void_unwrap() += 1;
+        assert_eq!(job.get::<Squared>(), Ok(10));
+
+        assert_eq!(job.compute::<Squared>(), Ok(16));
+        assert_eq!(job.insert::<Squared>(1), Some(10));
+        assert_eq!(job.take::<Squared>(), Some(1));
+        assert_eq!(job.get::<Squared>(), Ok(16));
+    }
+
+    #[cfg(feature = "stats")]
+    #[test] fn test_group_counts() {
+        let mut extended = Extended::new();
+        assert!(extended.group_counts().is_empty());
+
+        extended.register_in_group::<One>("numbers");
+        extended.register_in_group::<Two>("numbers");
+        extended.register_in_group::<Three>("others");
+        extended.get::<One>().void_unwrap();
+        extended.get::<Two>().void_unwrap();
+
+        let counts = extended.group_counts();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["numbers"], 2);
+        assert_eq!(counts["others"], 0);
+    }
+
+    #[test] fn test_capture_refs() {
+        struct Refs<'a> { one: &'a One, two: &'a Two }
+
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        assert!(capture_refs!(extended, Refs { one: One, two: Two }).is_none());
+
+        extended.get::<Two>().void_unwrap();
+        let refs = capture_refs!(extended, Refs { one: One, two: Two }).unwrap();
+        assert_eq!((refs.one, refs.two), (&One(1), &Two(2)));
+    }
+
+    #[test] fn test_reinitialize() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+
+        let old = extended.reinitialize();
+        assert_eq!(old.get::<One>(), Some(&One(1)));
+        assert!(extended.extensions().is_empty());
+    }
+
+    #[test] fn test_clear_with() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        extended.insert::<IntKey>(2);
+
+        let mut ones = Vec::new();
+        extended.clear_with(|id, value| if id == TypeId::of::<One>() {
+            ones.push(*value.downcast::<One>().unwrap())
+        });
+        assert_eq!(ones, vec![One(1)]);
+        assert!(extended.extensions().is_empty());
+    }
+
+    #[test] fn test_get_first() {
+        struct Env;
+        impl Key for Env { type Value = &'static str; }
+        impl Plugin<Extended> for Env {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<&'static str, &'static str> { Err("no env") }
+        }
+
+        struct File;
+        impl Key for File { type Value = &'static str; }
+        impl Plugin<Extended> for File {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<&'static str, &'static str> { Ok("file") }
+        }
+
+        struct Remote;
+        impl Key for Remote { type Value = &'static str; }
+        impl Plugin<Extended> for Remote {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<&'static str, &'static str> { Err("no remote") }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(get_first!(extended, Env, File, Remote), Ok("file"));
+        assert_eq!(extended.peek::<File>(), Some(&"file"));
+        assert_eq!(extended.peek::<Remote>(), None);
+        assert_eq!(get_first!(extended, Env, Remote), Err("no remote"));
+    }
+
+    #[test] fn test_insert_boxed() {
+        let mut worker = Extended::new();
+        let two = worker.compute_boxed::<Two>().void_unwrap();
+
+        let mut extended = Extended::new();
+        let two = extended.insert_boxed(TypeId::of::<Two>(), two).unwrap_err();
+
+        extended.register_boxed::<One>();
+        extended.register_boxed::<Two>();
+        let two = extended.insert_boxed(TypeId::of::<One>(), two).unwrap_err();
+        assert!(extended.insert_boxed(TypeId::of::<Two>(), two).is_ok());
+        assert_eq!(extended.peek::<Two>(), Some(&Two(2)));
+        assert_eq!(extended.peek::<One>(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_set_observer() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut extended = Extended::new();
+        let observed = seen.clone();
+        extended.set_observer(Box::new(move |id, _| observed.borrow_mut().push(id)));
+
+        extended.get::<One>().void_unwrap();
+        extended.get::<One>().void_unwrap();
+        extended.get::<Two>().void_unwrap();
+        extended.get_rc::<Two>().void_unwrap();
+        assert_eq!(*seen.borrow(), vec![TypeId::of::<One>(), TypeId::of::<Two>(), TypeId::of::<Two>()]);
+    }
+
+    #[test] fn test_clone_into_buffer() {
+        struct Name;
+        impl Key for Name { type Value = String; }
+        impl Plugin<Extended> for Name {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<String, Void> { Ok("plugin".to_string()) }
+        }
+
+        let mut extended = Extended::new();
+        let mut buffer = String::with_capacity(64);
+        extended.clone_into_buffer::<Name>(&mut buffer).void_unwrap();
+        assert_eq!(buffer, "plugin");
+        assert!(buffer.capacity() >= 64);
+    }
+
+    #[test] fn test_extensions_hash() {
+        use super::HashablePlugin;
+
+        struct Hashed;
+        impl Key for Hashed { type Value = i32; }
+        impl HashablePlugin for Hashed {}
+
+        struct Unhashed;
+        impl Key for Unhashed { type Value = i32; }
+
+        let mut first = Extended::new();
+        first.register_hashable::<Hashed>();
+        let mut second = Extended::new();
+        second.register_hashable::<Hashed>();
+        assert_eq!(first.extensions_hash(), second.extensions_hash());
+
+        first.insert::<Hashed>(1);
+        first.insert::<Unhashed>(2);
+        assert!(first.extensions_hash() != second.extensions_hash());
+
+        second.insert::<Hashed>(1);
+        assert_eq!(first.extensions_hash(), second.extensions_hash());
+    }
+
+    #[test] fn test_with_parent() {
+        use super::WithParent;
+
+        struct Child;
+        impl Key for Child { type Value = i32; }
+        impl<'p> Plugin<WithParent<'p, Extended>> for Child {
+            type Error = Void;
+            fn eval(_: &mut WithParent<'p, Extended>) -> Result<i32, Void> { Ok(3) }
+        }
+
+        let mut parent = Extended::new();
+        parent.insert::<Child>(1);
+
+        let mut borrowing = WithParent::new(Some(&parent), false);
+        assert_eq!(borrowing.get_ref::<Child>(), Ok(&1));
+        assert_eq!(borrowing.get::<Child>(), Ok(1));
+        assert_eq!(borrowing.compute_if_absent::<Child>(), Ok(1));
+        assert!(borrowing.extensions().is_empty());
+
+        fn generic<E: Pluggable + Extensible>(ext: &mut E) -> i32 where Child: Plugin<E, Error = Void> {
+            ext.get::<Child>().void_unwrap()
+        }
+        assert_eq!(generic(&mut borrowing), 1);
+
+        let mut cloning = WithParent::new(Some(&parent), true);
+        assert_eq!(cloning.get_ref::<Child>(), Ok(&1));
+        assert_eq!(cloning.peek::<Child>(), None);
+        assert_eq!(cloning.get::<Child>(), Ok(1));
+        assert_eq!(cloning.peek::<Child>(), Some(&1));
+
+        let mut orphan = WithParent::<Extended>::new(None, false);
+        assert_eq!(orphan.get_ref::<Child>(), Ok(&3));
+    }
+
+    #[test] fn test_insert_missing_boxed() {
+        use std::any::Any;
+
+        let mut extended = Extended::new();
+        extended.register_boxed::<One>();
+        extended.register_boxed::<Two>();
+        extended.insert::<One>(One(1));
+
+        let values: Vec<(TypeId, Box<dyn Any>)> = vec![
+            (TypeId::of::<One>(), Box::new(One(10))),
+            (TypeId::of::<Two>(), Box::new(Two(20))),
+            (TypeId::of::<Three>(), Box::new(Three(30)))
+        ];
+        assert_eq!(extended.insert_missing_boxed(values), 1);
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+        assert_eq!(extended.peek::<Two>(), Some(&Two(20)));
+        assert_eq!(extended.peek::<Three>(), None);
+    }
+
+    #[test] fn test_get_depth_limited() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::DepthError;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        // Resolves itself as its own dependency, descending forever.
+        struct Descend;
+        impl Key for Descend { type Value = i32; }
+        impl Plugin<Extended> for Descend {
+            type Error = &'static str;
+            fn eval(ext: &mut Extended) -> Result<i32, &'static str> {
+                EVALS.fetch_add(1, Ordering::SeqCst);
+                match ext.get_depth_limited::<Descend>() {
+                    Err(DepthError::DepthLimitExceeded) => Err("too deep"),
+                    Err(DepthError::Plugin(err)) => Err(err),
+                    _ => Ok(0)
+                }
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.set_max_depth(5), None);
+        assert_eq!(extended.get_depth_limited::<Descend>(), Err(DepthError::Plugin("too deep")));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 5);
+
+        assert_eq!(extended.get_depth_limited::<One>(), Ok(One(1)));
+        extended.set_max_depth(0);
+        assert_eq!(extended.get_depth_limited::<One>(), Ok(One(1)));
+        assert_eq!(extended.get_depth_limited::<Two>(), Err(DepthError::DepthLimitExceeded));
+    }
+
+    #[test] fn test_get_with_fuel() {
+        use super::FuelError;
+
+        // Each level resolves the next one, down to `Leaf`.
+        struct Leaf;
+        impl Key for Leaf { type Value = i32; }
+        impl Plugin<Extended> for Leaf {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<i32, &'static str> { Ok(1) }
+        }
+
+        struct Middle;
+        impl Key for Middle { type Value = i32; }
+        impl Plugin<Extended> for Middle {
+            type Error = &'static str;
+            fn eval(ext: &mut Extended) -> Result<i32, &'static str> {
+                match ext.get_with_fuel::<Leaf>(usize::MAX) {
+                    Ok(leaf) => Ok(leaf + 1),
+                    Err(FuelError::FuelExhausted) => Err("out of fuel"),
+                    Err(FuelError::Plugin(err)) => Err(err)
+                }
+            }
+        }
+
+        struct Top;
+        impl Key for Top { type Value = i32; }
+        impl Plugin<Extended> for Top {
+            type Error = &'static str;
+            fn eval(ext: &mut Extended) -> Result<i32, &'static str> {
+                match ext.get_with_fuel::<Middle>(usize::MAX) {
+                    Ok(middle) => Ok(middle + 1),
+                    Err(FuelError::FuelExhausted) => Err("out of fuel"),
+                    Err(FuelError::Plugin(err)) => Err(err)
+                }
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_with_fuel::<Top>(0), Err(FuelError::FuelExhausted));
+        assert_eq!(extended.get_with_fuel::<Top>(2), Err(FuelError::Plugin("out of fuel")));
+        assert_eq!(extended.peek::<Middle>(), None);
+
+        assert_eq!(extended.get_with_fuel::<Top>(3), Ok(3));
+        assert_eq!(extended.get_with_fuel::<Top>(0), Ok(3));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test] fn test_get_assert_once() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_assert_once::<One>(), Ok(&One(1)));
+        assert_eq!(extended.get_assert_once::<One>(), Ok(&One(1)));
+
+        extended.invalidate::<One>();
+        let again = panic::catch_unwind(AssertUnwindSafe(|| { extended.get_assert_once::<One>().ok(); }));
+        assert!(again.is_err());
+    }
+
+    #[test] fn test_invalidate_group() {
+        let mut extended = Extended::new();
+        extended.register_in_group::<One>("session");
+        extended.register_in_group::<Two>("session");
+        extended.get::<One>().void_unwrap();
+        extended.get::<Three>().void_unwrap();
+
+        assert_eq!(extended.invalidate_group("session"), 1);
+        assert_eq!(extended.peek::<One>(), None);
+        assert_eq!(extended.peek::<Three>(), Some(&Three(3)));
+        assert_eq!(extended.invalidate_group("session"), 0);
+        assert_eq!(extended.invalidate_group("other"), 0);
+    }
+
+    #[test] fn test_get_named_error() {
+        struct Failing;
+        impl Key for Failing { type Value = i32; }
+        impl Plugin<Extended> for Failing {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<i32, &'static str> { Err("failed") }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_named_error::<One>(), Ok(One(1)));
+        assert_eq!(extended.get_named_error::<Failing>(),
+                   Err((std::any::type_name::<Failing>(), "failed")));
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_get_warn_slow() {
+        use std::thread;
+        use std::time::Duration;
+
+        struct Sleepy;
+        impl Key for Sleepy { type Value = i32; }
+        impl Plugin<Extended> for Sleepy {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> {
+                thread::sleep(Duration::from_millis(10));
+                Ok(1)
+            }
+        }
+
+        let mut warned = Vec::new();
+        let mut extended = Extended::new();
+        let threshold = Duration::from_millis(5);
+        extended.get_warn_slow::<Sleepy, _>(threshold, |name, _| warned.push(name)).void_unwrap();
+        extended.get_warn_slow::<Sleepy, _>(threshold, |name, _| warned.push(name)).void_unwrap();
+        extended.get_warn_slow::<One, _>(Duration::from_secs(60), |name, _| warned.push(name)).void_unwrap();
+
+        assert_eq!(warned.len(), 1);
+        assert!(warned[0].ends_with("Sleepy"));
+    }
+
+    #[test] fn test_stream() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::vec;
+        use super::StreamingPlugin;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Lines;
+        impl Key for Lines { type Value = &'static str; }
+        impl StreamingPlugin<Extended> for Lines {
+            type Error = ();
+            type Stream = vec::IntoIter<Result<&'static str, ()>>;
+            const CACHE: bool = true;
+
+            fn eval(_: &mut Extended) -> Self::Stream {
+                EVALS.fetch_add(1, Ordering::SeqCst);
+                vec![Ok("first"), Ok("second")].into_iter()
+            }
+        }
+
+        struct Pages;
+        impl Key for Pages { type Value = i32; }
+        impl StreamingPlugin<Extended> for Pages {
+            type Error = &'static str;
+            type Stream = vec::IntoIter<Result<i32, &'static str>>;
+
+            fn eval(_: &mut Extended) -> Self::Stream {
+                vec![Ok(1), Err("timeout")].into_iter()
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.stream::<Lines>().next(), Some(Ok("first")));
+        assert_eq!(extended.stream::<Lines>().count(), 2);
+        assert_eq!(EVALS.load(Ordering::SeqCst), 2);
+
+        let replayed: Vec<_> = extended.stream::<Lines>().collect();
+        assert_eq!(replayed, vec![Ok("first"), Ok("second")]);
+        assert_eq!(EVALS.load(Ordering::SeqCst), 2);
+
+        let pages: Vec<_> = extended.stream::<Pages>().collect();
+        assert_eq!(pages, vec![Ok(1), Err("timeout")]);
+    }
+
+    #[test] fn test_get_ref_if() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ref_if::<Two, _>(|&Two(n)| n > 5), Ok(None));
+        assert_eq!(extended.peek::<Two>(), Some(&Two(2)));
+        assert_eq!(extended.get_ref_if::<Two, _>(|&Two(n)| n == 2), Ok(Some(&Two(2))));
+    }
+
+    #[test] fn test_restore_from() {
+        let mut source = Extended::new();
+        source.get::<One>().void_unwrap();
+        source.get::<Two>().void_unwrap();
+
+        let mut kept = Extended::new();
+        kept.insert::<One>(One(10));
+        kept.restore_from(export!(source, One, Two), false);
+        assert_eq!(kept.peek::<One>(), Some(&One(10)));
+        assert_eq!(kept.peek::<Two>(), Some(&Two(2)));
+
+        let mut replaced = Extended::new();
+        replaced.insert::<One>(One(10));
+        replaced.restore_from(export!(source, One, Two), true);
+        assert_eq!(replaced.peek::<One>(), Some(&One(1)));
+        assert_eq!(replaced.peek::<Two>(), Some(&Two(2)));
+    }
+
+    #[test] fn test_get_shared() {
+        use std::sync::Arc;
+
+        let mut extended = Extended::new();
+        let first = extended.get_shared::<One>().void_unwrap();
+        let second = extended.get_shared::<One>().void_unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        extended.invalidate::<One>();
+        let third = extended.get_shared::<One>().void_unwrap();
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert_eq!(*third, One(1));
+    }
+
+    #[test] fn test_get_shared_evaluates_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted;
+        impl Key for Counted { type Value = i32; }
+        impl Plugin<Extended> for Counted {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> {
+                Ok(EVALS.fetch_add(1, Ordering::SeqCst) as i32)
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get::<Counted>(), Ok(0));
+        assert_eq!(*extended.get_shared::<Counted>().void_unwrap(), 0);
+        assert_eq!(extended.get_ref::<Counted>(), Ok(&0));
+        assert_eq!(extended.get::<Counted>(), Ok(0));
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test] fn test_get_shared_then_modify() {
+        use std::sync::Arc;
+
+        let mut extended = Extended::new();
+        extended.get_shared::<One>().void_unwrap();
+        assert_eq!(extended.peek::<One>(), Some(&One(1)));
+        extended.get_mut::<One>().void_unwrap().0 = 5;
+        assert_eq!(*extended.get_shared::<One>().void_unwrap(), One(5));
+
+        assert_eq!(extended.insert::<One>(One(6)), Some(One(5)));
+        assert_eq!(extended.get::<One>(), Ok(One(6)));
+        assert_eq!(*extended.get_shared::<One>().void_unwrap(), One(6));
+
+        assert_eq!(extended.take::<One>(), Some(One(6)));
+        assert_eq!(extended.peek::<One>(), None);
+
+        let held = extended.get_shared::<One>().void_unwrap();
+        assert_eq!(extended.insert::<One>(One(7)), None);
+        assert_eq!(*extended.get_shared::<One>().void_unwrap(), One(7));
+        assert_eq!(extended.get::<One>(), Ok(One(7)));
+        assert_eq!(*held, One(1));
+        assert!(!Arc::ptr_eq(&held, &extended.get_shared::<One>().void_unwrap()));
+    }
+
+    #[test] fn test_transaction() {
+        struct Failing;
+        impl Key for Failing { type Value = One; }
+        impl Plugin<Extended> for Failing {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<One, &'static str> { Err("failed") }
+        }
+
+        struct Minted;
+        impl Key for Minted { type Value = One; }
+        impl Plugin<Extended> for Minted {
+            type Error = &'static str;
+            fn eval(_: &mut Extended) -> Result<One, &'static str> { Ok(One(5)) }
+        }
+
+        // Caches `Minted` as a dependency, then fails.
+        struct Dependent;
+        impl Key for Dependent { type Value = One; }
+        impl Plugin<Extended> for Dependent {
+            type Error = &'static str;
+            fn eval(ext: &mut Extended) -> Result<One, &'static str> {
+                ext.get::<Minted>()?;
+                Err("dependent failed")
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(transaction!(extended, [Minted, Failing]), Err("failed"));
+        assert_eq!(extended.peek::<Minted>(), None);
+
+        assert_eq!(transaction!(extended, [Dependent, Minted]), Err("dependent failed"));
+        assert_eq!(extended.peek::<Minted>(), None);
+
+        extended.insert::<Minted>(One(1));
+        assert_eq!(transaction!(extended, [Minted, Failing]), Err("failed"));
+        assert_eq!(extended.peek::<Minted>(), Some(&One(1)));
+
+        assert_eq!(transaction!(extended, [Two, Three]), Ok(()));
+        assert_eq!(extended.peek::<Two>(), Some(&Two(2)));
+        assert_eq!(extended.peek::<Three>(), Some(&Three(3)));
+    }
+
+    #[test] fn test_get_ref_counting_miss() {
+        let mut misses = Vec::new();
+        let mut extended = Extended::new();
+        for _ in 0..3 {
+            extended.get_ref_counting_miss::<One, _>(|name| misses.push(name)).void_unwrap();
+        }
+
+        assert_eq!(misses.len(), 1);
+        assert!(misses[0].ends_with("One"));
+    }
+
+    #[test] fn test_pluggable_read() {
+        use super::{NotCached, PluggableRead};
+
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+
+        let readers = &extended;
+        assert_eq!(readers.get_cached::<One>(), Ok(&One(1)));
+        let missing = readers.get_cached::<Two>().unwrap_err();
+        assert_eq!(missing, NotCached { plugin: std::any::type_name::<Two>() });
+        assert_eq!(missing.to_string(), format!("plugin `{}` is not cached", missing.plugin));
+
+        struct Current;
+        impl Key for Current { type Value = Two; }
+
+        extended.get_shared::<Three>().void_unwrap();
+        extended.register_alias::<Two, Current>();
+        extended.insert::<Current>(Two(2));
+
+        let readers = &extended;
+        assert_eq!(readers.get_cached::<Two>(), Ok(&Two(2)));
+        assert_eq!(readers.get_cached::<Three>(), Ok(&Three(3)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test] fn test_get_budgeted() {
+        use std::thread;
+        use std::time::Duration;
+        use super::DeadlineError;
+
+        struct Costly;
+        impl Key for Costly { type Value = i32; }
+        impl Plugin<Extended> for Costly {
+            type Error = Void;
+            fn eval(_: &mut Extended) -> Result<i32, Void> {
+                thread::sleep(Duration::from_millis(20));
+                Ok(1)
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_budgeted::<One>(), Ok(One(1)));
+
+        extended.set_budget(Duration::from_millis(10));
+        assert_eq!(extended.get_budgeted::<Costly>(), Err(DeadlineError::BudgetExhausted));
+        assert_eq!(extended.peek::<Costly>(), Some(&1));
+        assert_eq!(extended.budget(), Some(Duration::from_secs(0)));
+
+        assert_eq!(extended.get_budgeted::<Costly>(), Ok(1));
+        assert_eq!(extended.get_budgeted::<Two>(), Err(DeadlineError::BudgetExhausted));
+        assert_eq!(extended.peek::<Two>(), None);
+    }
+
+    #[test] fn test_trace_id() {
+        struct Logged;
+        impl Key for Logged { type Value = String; }
+        impl Plugin<Extended> for Logged {
+            type Error = Void;
+            fn eval(ext: &mut Extended) -> Result<String, Void> {
+                Ok(format!("[{}] evaluated", ext.current_trace_id().unwrap_or("-")))
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.current_trace_id(), None);
+        assert_eq!(extended.compute::<Logged>(), Ok("[-] evaluated".to_string()));
+
+        assert_eq!(extended.set_trace_id("req-1"), None);
+        assert_eq!(extended.get::<Logged>(), Ok("[req-1] evaluated".to_string()));
+        assert_eq!(extended.set_trace_id("req-2"), Some("req-1".to_string()));
+        assert_eq!(extended.current_trace_id(), Some("req-2"));
+    }
+
+    #[test] fn test_get_ref_versioned() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_ref_versioned::<One>(), Ok((&One(1), 0)));
+
+        assert_eq!(extended.advance_generation(), 1);
+        assert_eq!(extended.get_ref_versioned::<Two>(), Ok((&Two(2), 1)));
+        assert_eq!(extended.get_ref_versioned::<One>(), Ok((&One(1), 0)));
+
+        assert_eq!(extended.advance_generation(), 2);
+        extended.invalidate::<One>();
+        assert_eq!(extended.get_ref_versioned::<One>(), Ok((&One(1), 2)));
+        assert_eq!(extended.get_ref_versioned::<Two>(), Ok((&Two(2), 1)));
+        assert_eq!(extended.generation(), 2);
+
+        extended.insert::<Two>(Two(5));
+        assert_eq!(extended.get_ref_versioned::<Two>(), Ok((&Two(5), 0)));
+        extended.take::<Two>();
+        extended.insert::<Two>(Two(6));
+        assert_eq!(extended.get_ref_versioned::<Two>(), Ok((&Two(6), 0)));
+    }
+
+    #[test] fn test_generation_sensitive() {
+        use super::GenerationSensitive;
+
+        struct Snapshot;
+        impl Key for Snapshot { type Value = u64; }
+        impl Plugin<Extended> for Snapshot {
+            type Error = Void;
+            fn eval(extended: &mut Extended) -> Result<u64, Void> { Ok(extended.generation()) }
+        }
+        impl GenerationSensitive for Snapshot {}
+
+        let mut extended = Extended::new();
+        extended.get::<Snapshot>().void_unwrap();
+        extended.advance_generation();
+        assert_eq!(extended.get::<Snapshot>(), Ok(0));
+
+        extended.register_generation_sensitive::<Snapshot>();
+        assert_eq!(extended.get::<Snapshot>(), Ok(1));
+        assert_eq!(extended.get::<Snapshot>(), Ok(1));
+
+        extended.advance_generation();
+        assert_eq!(extended.get::<Snapshot>(), Ok(2));
+    }
+
+    #[test] fn test_generation_sensitive_fast_paths() {
+        use super::GenerationSensitive;
+
+        struct Snapshot;
+        impl Key for Snapshot { type Value = u64; }
+        impl Plugin<Extended> for Snapshot {
+            type Error = Void;
+            fn eval(extended: &mut Extended) -> Result<u64, Void> { Ok(extended.generation()) }
+        }
+        impl GenerationSensitive for Snapshot {}
+
+        let mut extended = Extended::new();
+        extended.register_generation_sensitive::<Snapshot>();
+        assert_eq!(extended.get_cache_if::<Snapshot, _>(|_| true), Ok(0));
+
+        extended.advance_generation();
+        assert_eq!(extended.compute_if_absent::<Snapshot>(), Ok(1));
+        assert_eq!(extended.get_with_fuel::<Snapshot>(1).ok(), Some(1));
+        assert_eq!(extended.get_depth_limited::<Snapshot>().ok(), Some(1));
+
+        extended.advance_generation();
+        assert_eq!(extended.get_depth_limited::<Snapshot>().ok(), Some(2));
+        assert_eq!(extended.get::<Snapshot>(), Ok(2));
+    }
+
+    #[test] fn test_combine() {
+        use super::CombineError;
+
+        struct Failing;
+
+        impl Key for Failing { type Value = i32; }
+
+        impl Plugin<Extended> for Failing {
+            type Error = ();
+
+            fn eval(_: &mut Extended) -> Result<i32, ()> { Err(()) }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.combine::<One, Two, _, _>(|one, two| one.0 + two.0), Ok(3));
+        assert_eq!((extended.peek::<One>(), extended.peek::<Two>()), (Some(&One(1)), Some(&Two(2))));
+
+        assert_eq!(extended.combine::<Failing, One, _, _>(|_, _| ()), Err(CombineError::First(())));
+        assert_eq!(extended.combine::<One, Failing, _, _>(|_, _| ()), Err(CombineError::Second(())));
+    }
+
+    #[test] fn test_try_map_cached() {
+        let mut extended = Extended::new();
+        assert_eq!(extended.try_map_cached::<IntKey, (), _>(|n| Ok(n + 1)), Ok(false));
+
+        extended.insert::<IntKey>(1);
+        assert_eq!(extended.try_map_cached::<IntKey, (), _>(|n| Ok(n + 1)), Ok(true));
+        assert_eq!(extended.try_map_cached::<IntKey, _, _>(|n| Err((n, "rejected"))), Err("rejected"));
+        assert_eq!(extended.peek::<IntKey>(), Some(&2));
+    }
+
+    #[test] fn test_fallback() {
+        struct Flaky;
+
+        impl Key for Flaky { type Value = i32; }
+
+        impl Plugin<Extended> for Flaky {
+            type Error = ();
+
+            fn eval(ext: &mut Extended) -> Result<i32, ()> {
+                if ext.extensions().contains::<One>() { Ok(1) } else { Err(()) }
+            }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get::<Flaky>(), Err(()));
+
+        assert_eq!(extended.set_fallback::<Flaky>(7), None);
+        assert_eq!(extended.get::<Flaky>(), Ok(7));
+
+        extended.get::<One>().void_unwrap();
+        assert_eq!(extended.get::<Flaky>(), Ok(1));
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test] fn test_assert_computed_since() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+
+        let checkpoint = extended.checkpoint();
+        assert_eq!(checkpoint.len(), 1);
+        extended.get::<One>().void_unwrap();
+        extended.get::<Two>().void_unwrap();
+        extended.get::<Three>().void_unwrap();
+        assert_computed_since!(extended, checkpoint, [Three, Two]);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test] #[should_panic(expected = "new keys were computed")]
+    fn test_assert_computed_since_extra() {
+        let mut extended = Extended::new();
+        let checkpoint = extended.checkpoint();
+        extended.get::<One>().void_unwrap();
+        extended.get::<Two>().void_unwrap();
+        assert_computed_since!(extended, checkpoint, [One]);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test] fn test_eval_count() {
+        let mut extended = Extended::new();
+        extended.get::<One>().void_unwrap();
+        assert_eq!(extended.eval_count::<One>(), 0);
+
+        extended.track_evals::<One>();
+        extended.invalidate::<One>();
+        for _ in 0..5 { extended.get::<One>().void_unwrap(); }
+        assert_eq!(extended.eval_count::<One>(), 1);
+
+        extended.get_rc::<One>().void_unwrap();
+        assert!(extended.get_result::<One>().is_ok());
+        extended.invalidate::<One>();
+        extended.get_shared::<One>().void_unwrap();
+        assert_eq!(extended.eval_count::<One>(), 4);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test] fn test_order_independent() {
+        assert_order_independent!(Extended::new, [One, Two, Three]);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test] #[should_panic] fn test_order_dependent() {
+        struct SeesOne;
+
+        impl Key for SeesOne { type Value = bool; }
+
+        impl Plugin<Extended> for SeesOne {
+            type Error = Void;
+
+            fn eval(ext: &mut Extended) -> Result<bool, Void> {
+                Ok(ext.extensions().contains::<One>())
+            }
+        }
+
+        assert_order_independent!(Extended::new, [One, SeesOne]);
+    }
+
+    #[cfg(feature = "thread")]
+    #[test] fn test_prefetch() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::PrefetchPlugin;
+
+        static EVALS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Slow;
+
+        impl Key for Slow { type Value = i32; }
+
+        impl Plugin<Extended> for Slow {
+            type Error = Void;
+
+            fn eval(ext: &mut Extended) -> Result<i32, Void> {
+                Slow::eval_input(Slow::input(ext))
+            }
+        }
+
+        impl PrefetchPlugin<Extended> for Slow {
+            type Input = i32;
+
+            fn input(_: &mut Extended) -> i32 { 20 }
+
+            fn eval_input(input: i32) -> Result<i32, Void> {
+                EVALS.fetch_add(1, Ordering::SeqCst);
+                Ok(input + 1)
+            }
+        }
+
+        let mut extended = Extended::new();
+        extended.prefetch::<Slow>();
+        extended.prefetch::<Slow>();
+        assert_eq!(extended.get::<Slow>().void_unwrap(), 21);
+        assert_eq!(extended.get::<Slow>().void_unwrap(), 21);
+        assert_eq!(EVALS.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "thread")]
+    #[test] fn test_get_stale_while_revalidate() {
+        use std::sync::atomic::{AtomicI32, Ordering};
+        use std::thread;
+        use std::time::Duration;
+        use super::{PrefetchPlugin, TtlPlugin};
+
+        static VERSION: AtomicI32 = AtomicI32::new(0);
+
+        struct Stale;
+
+        impl Key for Stale { type Value = i32; }
+
+        impl Plugin<Extended> for Stale {
+            type Error = Void;
+
+            fn eval(ext: &mut Extended) -> Result<i32, Void> {
+                Stale::eval_input(Stale::input(ext))
+            }
+        }
+
+        impl PrefetchPlugin<Extended> for Stale {
+            type Input = i32;
+
+            fn input(_: &mut Extended) -> i32 { 1 }
+
+            fn eval_input(step: i32) -> Result<i32, Void> {
+                Ok(VERSION.fetch_add(step, Ordering::SeqCst) + step)
+            }
+        }
+
+        impl TtlPlugin<Extended> for Stale {
+            fn ttl() -> Duration { Duration::from_secs(0) }
+        }
+
+        let mut extended = Extended::new();
+        assert_eq!(extended.get_stale_while_revalidate::<Stale>().void_unwrap(), 1);
+        assert_eq!(extended.get_stale_while_revalidate::<Stale>().void_unwrap(), 1);
+
+        let refreshed = (0..1000).any(|_| {
+            thread::sleep(Duration::from_millis(1));
+            extended.get_stale_while_revalidate::<Stale>().void_unwrap() > 1
+        });
+        assert!(refreshed);
+    }
+}
@@ -0,0 +1,110 @@
+//! The cache lookup in front of every method which may evaluate a plugin.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use typemap::Key;
+
+#[cfg(feature = "std")]
+use observe;
+#[cfg(feature = "test-util")]
+use test_util;
+use {alias, version};
+use {ArcValue, ExtensibleStore, ExtensionStore, Plugin, Pluggable};
+
+// Discards the plugin's cached value if it was computed before the current
+// generation, so that the caller evaluates the plugin again.
+pub fn refresh<P: Key, E>(ext: &mut E)
+where P::Value: Any, E: Pluggable + ExtensibleStore + ?Sized {
+    if version::is_stale::<P, _>(ext.store()) {
+        ext.invalidate::<P>();
+    }
+}
+
+// The plugin's value in the type's own storage, or behind the handle of
+// `get_shared`, or else in its parent's storage, following an alias
+// registered for the plugin.
+pub fn find<P: Key, E>(ext: &E) -> Option<&P::Value>
+where P::Value: Any, E: ExtensibleStore + ?Sized {
+    alias::get::<P, _>(ext.store())
+        .or_else(|| ext.store().get::<ArcValue<P>>().map(|value| &**value))
+        .or_else(|| ext.parent_store().and_then(alias::get::<P, _>))
+}
+
+// Whether the type's own storage holds a value for the plugin, in its slot
+// or behind the handle of `get_shared`.
+pub fn is_cached<P: Key, S: ExtensionStore + 'static>(store: &S) -> bool {
+    alias::contains::<P, _>(store) || store.contains::<ArcValue<P>>()
+}
+
+// Moves a value held behind the handle of `get_shared` back into the
+// plugin's own slot, so that it can be replaced, removed or borrowed
+// mutably. If other handles still share the value, the cache's handle is
+// dropped instead, and the value is no longer cached.
+pub fn unshare<P: Key, S: ExtensionStore + 'static>(store: &mut S) {
+    if let Some(shared) = store.remove::<ArcValue<P>>() {
+        if let Ok(value) = Arc::try_unwrap(shared) {
+            alias::insert::<P, _>(store, value);
+        }
+    }
+}
+
+// The plugin's cached value, as found by `find`, if it is still current.
+pub fn cached<P: Key, E>(ext: &mut E) -> Option<&P::Value>
+where P::Value: Any, E: Pluggable + ExtensibleStore + ?Sized {
+    refresh::<P, E>(ext);
+    find::<P, E>(ext)
+}
+
+// A copy of the plugin's cached value, as found by `cached`. A value found
+// in the parent's storage is also cloned into the type's own storage, if
+// the type asks for that.
+pub fn cloned<P: Key, E>(ext: &mut E) -> Option<P::Value>
+where P::Value: Clone + Any, E: Pluggable + ExtensibleStore + ?Sized {
+    refresh::<P, E>(ext);
+    if let Some(value) = alias::get::<P, _>(ext.store()) {
+        return Some(value.clone());
+    }
+    if let Some(value) = ext.store().get::<ArcValue<P>>() {
+        return Some((**value).clone());
+    }
+
+    let value = ext.parent_store().and_then(alias::get::<P, _>)?.clone();
+    if ext.clones_from_parent_store() {
+        alias::insert::<P, _>(ext.store_mut(), value.clone());
+    }
+    Some(value)
+}
+
+// Caches a freshly evaluated value, stamped with the current generation,
+// under the key the plugin is aliased to, if any.
+pub fn store<P: Key, E>(ext: &mut E, value: P::Value) -> &mut P::Value
+where P::Value: Any, E: ExtensibleStore + ?Sized {
+    version::stamp::<P, _>(ext.store_mut());
+    alias::insert::<P, _>(ext.store_mut(), value)
+}
+
+// Evaluates the plugin, counting the evaluation for `Pluggable::eval_count`
+// and reporting it to the observer installed with `Pluggable::set_observer`.
+pub fn eval<P: Plugin<E>, E: ExtensibleStore + ?Sized>(ext: &mut E) -> Result<P::Value, P::Error> {
+    eval_with::<P, E, _, _>(ext, P::eval)
+}
+
+// Like `eval`, for plugins evaluated through another trait, such as
+// `DiagnosticPlugin`.
+#[cfg_attr(not(any(feature = "std", feature = "test-util")), allow(clippy::extra_unused_type_parameters))]
+pub fn eval_with<P: Key, E: ExtensibleStore + ?Sized, R, F>(ext: &mut E, eval: F) -> R
+where F: FnOnce(&mut E) -> R {
+    #[cfg(feature = "test-util")]
+    {
+        if let Some(count) = ext.store_mut().get_mut::<test_util::EvalCount<P>>() {
+            *count += 1;
+        }
+    }
+
+    #[cfg(feature = "std")]
+    let result = observe::eval::<P, E, _, _>(ext, eval);
+    #[cfg(not(feature = "std"))]
+    let result = eval(ext);
+    result
+}
@@ -0,0 +1,359 @@
+//! Macros operating on several plugins at once.
+
+use std::mem;
+
+use typemap::{TypeMap, Key};
+
+use {Extensible, ExtensibleStore, ExtensionStore, Fallback, Plugin, Pluggable};
+use {evict, lookup};
+
+#[cfg(feature = "thread")]
+use {scoped, PluginRef, SyncExtensible};
+
+/// Cache values for several plugins without evaluating them.
+///
+/// `seed!(ctx, A => a, B => b)` expands to a call to `Pluggable::insert`
+/// for each pair, so every value is type-checked against its plugin's
+/// `Key::Value`. Any previously cached values are replaced.
+#[macro_export]
+macro_rules! seed {
+    ($ctx:expr, $($plugin:ty => $value:expr),+ $(,)*) => {{
+        use $crate::Pluggable;
+
+        let ctx = &mut $ctx;
+        $(ctx.insert::<$plugin>($value);)+
+    }}
+}
+
+/// Borrow the values of several plugins at once.
+///
+/// `get_ref_many!(ctx, A, B, C)` first makes sure each plugin is cached,
+/// evaluating them in order, and only then borrows all of their values
+/// through `Pluggable::peek`. Because every evaluation has finished by then,
+/// the borrows are shared and never overlap a mutable one. A plugin whose
+/// evaluation failed but which has a fallback borrows the fallback.
+///
+/// Expands to a `Result` holding a tuple of references. The listed plugins
+/// must share an error type: the first evaluation error aborts the whole
+/// expansion before any reference is produced.
+#[macro_export]
+macro_rules! get_ref_many {
+    ($ctx:expr, $($plugin:ty),+ $(,)*) => {{
+        let ctx = &mut $ctx;
+        'get_ref_many: {
+            $(if let Err(err) = $crate::Pluggable::ensure::<$plugin>(&mut *ctx) {
+                break 'get_ref_many Err(err);
+            })+
+
+            let ctx = &*ctx;
+            Ok(($($crate::macros::peek_evaluated::<$plugin, _>(ctx),)+))
+        }
+    }}
+}
+
+/// Bundle references to several cached plugin values into a struct.
+///
+/// `capture_refs!(ctx, Refs { a: A, b: B })` builds the struct literal
+/// `Refs { a, b }`, each field borrowing its plugin's cached value through
+/// `Pluggable::peek`. `Refs` is a struct defined by the caller, with fields
+/// of type `&A::Value` and so on. Nothing is evaluated.
+///
+/// Expands to `Some(Refs { .. })`, or `None` if any listed plugin isn't
+/// cached. The struct borrows `ctx` immutably for as long as it lives.
+#[macro_export]
+macro_rules! capture_refs {
+    ($ctx:expr, $name:ident { $($field:ident: $plugin:ty),+ $(,)* }) => {{
+        let ctx = &$ctx;
+        'capture_refs: {
+            Some($name { $($field: match $crate::Pluggable::peek::<$plugin>(ctx) {
+                Some(value) => value,
+                None => break 'capture_refs None
+            }),+ })
+        }
+    }}
+}
+
+/// Use a plugin's value together with a mutable field of the extended type.
+///
+/// `with_plugin_and_field!(ctx, P, field, |value, field| ...)` takes `P`'s
+/// value out of the cache, evaluating the plugin if it isn't cached, calls
+/// the closure with mutable references to the value and to `ctx.field`, and
+/// then puts the value back. This sidesteps the conflict between borrowing
+/// a cached value and borrowing a sibling field.
+///
+/// Expands to `Result<R, P::Error>`, where `R` is the closure's return type.
+/// If the closure panics, the taken value is dropped instead of restored.
+#[macro_export]
+macro_rules! with_plugin_and_field {
+    ($ctx:expr, $plugin:ty, $field:ident, $f:expr) => {{
+        let ctx = &mut $ctx;
+        match $crate::macros::take_or_eval::<$plugin, _>(&mut *ctx) {
+            Ok(mut value) => {
+                let result = ($f)(&mut value, &mut ctx.$field);
+                $crate::Pluggable::insert::<$plugin>(&mut *ctx, value);
+                Ok(result)
+            },
+            Err(err) => Err(err)
+        }
+    }}
+}
+
+/// Evaluate several plugins, collecting every outcome.
+///
+/// `get_results!(ctx, A, B, C)` calls `Pluggable::get` for each plugin in
+/// order and expands to a tuple of their `Result`s. Every plugin is
+/// evaluated even if an earlier one fails; cached values are reused as usual.
+#[macro_export]
+macro_rules! get_results {
+    ($ctx:expr, $($plugin:ty),+ $(,)*) => {{
+        let ctx = &mut $ctx;
+        ($($crate::Pluggable::get::<$plugin>(&mut *ctx),)+)
+    }}
+}
+
+/// Run a closure with several plugins' cached values temporarily overridden.
+///
+/// `with_overrides!(ctx, [A => a, B => b], |ctx| ...)` caches the given
+/// values in place of whatever `A` and `B` had cached, calls the closure
+/// with the context, and then restores each plugin's previous state: its
+/// prior value if it had one, otherwise nothing. Restoration happens even
+/// if the closure panics. Replaced and restored values do not run eviction
+/// hooks.
+///
+/// Expands to the closure's return value.
+#[macro_export]
+macro_rules! with_overrides {
+    ($ctx:expr, [$($plugin:ty => $value:expr),* $(,)*], $f:expr) => {{
+        let mut overrides = $crate::macros::Overrides::new(&mut $ctx);
+        $(overrides.set::<$plugin>($value);)*
+        overrides.run($f)
+    }}
+}
+
+/// Evaluate several independent plugins in parallel on scoped threads.
+///
+/// `warm_up_scoped!(ctx, A, B, C)` evaluates each listed `PluginRef` which
+/// isn't cached yet on its own thread, sharing `&ctx` between them, and
+/// caches the values in the context's shared extensions once every thread
+/// has finished, where `SyncPluggable::get_arc` finds them.
+///
+/// Since the threads share the context, the extended type must be `Sync`,
+/// which in practice means a `SyncExtensible` type holding no `TypeMap`;
+/// the plugins' values must be `Send` and `Sync`.
+///
+/// Errors are discarded and cache nothing, so a later evaluation of a
+/// failed plugin reports its error. A panic in any evaluation is
+/// propagated after all threads have finished. Requires the `thread`
+/// feature.
+#[cfg(feature = "thread")]
+#[macro_export]
+macro_rules! warm_up_scoped {
+    ($ctx:expr, $($plugin:ty),+ $(,)*) => {{
+        let ctx = &$ctx;
+        $crate::macros::run_scoped(ctx, &[$($crate::macros::eval_ref::<$plugin, _>),+]);
+    }}
+}
+
+/// Evaluate several plugins in order, returning the first success.
+///
+/// `get_first!(ctx, A, B, C)` calls `Pluggable::get` for `A`, then for `B`
+/// if `A` failed, and so on, stopping at the first plugin which succeeds.
+/// The listed plugins must share their value and error types.
+///
+/// Expands to the first successful value, or to the last plugin's error if
+/// every plugin fails. Only the successful plugin's value is cached; the
+/// plugins after it are not evaluated.
+#[macro_export]
+macro_rules! get_first {
+    (@try $ctx:ident, $plugin:ty) => {
+        $crate::Pluggable::get::<$plugin>(&mut *$ctx)
+    };
+    (@try $ctx:ident, $plugin:ty, $($rest:ty),+) => {
+        match $crate::Pluggable::get::<$plugin>(&mut *$ctx) {
+            Ok(value) => Ok(value),
+            Err(_) => $crate::get_first!(@try $ctx, $($rest),+)
+        }
+    };
+    ($ctx:expr, $($plugin:ty),+ $(,)*) => {{
+        let ctx = &mut $ctx;
+        $crate::get_first!(@try ctx, $($plugin),+)
+    }};
+}
+
+/// Evaluate several plugins as one unit, caching all of them or none.
+///
+/// `transaction!(ctx, [A, B, C])` makes sure each listed plugin is cached,
+/// evaluating them in order. If one fails, the listed plugins which were
+/// newly cached during the transaction are invalidated again, so the cache
+/// is left as it was before, and the error is returned. Plugins which were
+/// already cached stay cached either way.
+///
+/// Expands to `Result<(), E>`; the listed plugins must share an error type.
+#[macro_export]
+macro_rules! transaction {
+    ($ctx:expr, [$($plugin:ty),+ $(,)*]) => {{
+        let ctx = &mut $ctx;
+        let mut rollback: Vec<fn(&mut _)> = Vec::new();
+        $(if $crate::Pluggable::peek::<$plugin>(&*ctx).is_none() {
+            rollback.push($crate::macros::invalidate::<$plugin, _>);
+        })+
+        'transaction: {
+            $(if let Err(err) = $crate::Pluggable::ensure::<$plugin>(&mut *ctx) {
+                for undo in rollback { undo(&mut *ctx) }
+                break 'transaction Err(err);
+            })+
+            Ok(())
+        }
+    }}
+}
+
+/// Clear all cached plugin values except those of the listed plugins.
+///
+/// `invalidate_all_except!(ctx, A, B)` empties the context's extensions and
+/// then restores the values cached for `A` and `B`. Listed plugins which
+/// were not cached stay absent; nothing is evaluated. Everything else
+/// stored in the extensions, such as registered fallbacks, is cleared,
+/// running the eviction hooks of registered plugins.
+#[macro_export]
+macro_rules! invalidate_all_except {
+    ($ctx:expr $(, $plugin:ty)* $(,)*) => {{
+        let ctx = &mut $ctx;
+        let mut old = $crate::macros::take_extensions(ctx);
+        $(if let Some(value) = old.remove::<$plugin>() {
+            $crate::Extensible::extensions_mut(ctx).insert::<$plugin>(value);
+        })*
+        $crate::macros::evict_all(&mut old);
+    }}
+}
+
+/// Build a new `TypeMap` holding clones of the listed plugins' cached values.
+///
+/// `export!(ctx, A, B)` copies the values cached for `A` and `B`, if any,
+/// without evaluating anything. Another context can adopt the result
+/// through `Extensible::extensions_mut`. Plugin values must be `Clone`.
+#[macro_export]
+macro_rules! export {
+    ($ctx:expr, $($plugin:ty),+ $(,)*) => {{
+        use $crate::Extensible;
+
+        let ctx = &$ctx;
+        let mut map = $crate::macros::new_map();
+        $(if let Some(value) = ctx.extensions().get::<$plugin>() {
+            map.insert::<$plugin>(::std::clone::Clone::clone(value));
+        })+
+        map
+    }}
+}
+
+/// Check whether every listed plugin has a cached value.
+///
+/// `all_cached!(ctx, A, B, C)` expands to `Pluggable::peek` checks joined
+/// with `&&`, so checking stops at the first plugin which isn't cached.
+/// Nothing is evaluated.
+#[macro_export]
+macro_rules! all_cached {
+    ($ctx:expr, $($plugin:ty),+ $(,)*) => {{
+        let ctx = &$ctx;
+        $($crate::Pluggable::peek::<$plugin>(ctx).is_some())&&+
+    }}
+}
+
+/// Check whether any listed plugin has a cached value.
+///
+/// `any_cached!(ctx, A, B, C)` expands to `Pluggable::peek` checks joined
+/// with `||`, so checking stops at the first plugin which is cached.
+/// Nothing is evaluated.
+#[macro_export]
+macro_rules! any_cached {
+    ($ctx:expr, $($plugin:ty),+ $(,)*) => {{
+        let ctx = &$ctx;
+        $($crate::Pluggable::peek::<$plugin>(ctx).is_some())||+
+    }}
+}
+
+// Puts back one plugin's state from before it was overridden.
+type Restore<'a, E> = Box<dyn FnOnce(&mut E) + 'a>;
+
+// Restores the overridden values when dropped, including during a panic.
+#[doc(hidden)]
+pub struct Overrides<'a, E: ?Sized + 'a> {
+    ctx: &'a mut E,
+    restore: Vec<Restore<'a, E>>
+}
+
+impl<'a, E: ExtensibleStore + ?Sized + 'a> Overrides<'a, E> {
+    #[doc(hidden)]
+    pub fn new(ctx: &'a mut E) -> Self {
+        Overrides { ctx, restore: Vec::new() }
+    }
+
+    #[doc(hidden)]
+    pub fn set<P: Key>(&mut self, value: P::Value) {
+        let prior = self.ctx.store_mut().insert::<P>(value);
+        self.restore.push(Box::new(move |ctx: &mut E| match prior {
+            Some(prior) => { ctx.store_mut().insert::<P>(prior); },
+            None => { ctx.store_mut().remove::<P>(); }
+        }));
+    }
+
+    #[doc(hidden)]
+    pub fn run<F: FnOnce(&mut E) -> T, T>(self, f: F) -> T {
+        f(&mut *self.ctx)
+    }
+}
+
+impl<'a, E: ?Sized + 'a> Drop for Overrides<'a, E> {
+    fn drop(&mut self) {
+        while let Some(restore) = self.restore.pop() {
+            restore(&mut *self.ctx);
+        }
+    }
+}
+
+#[cfg(feature = "thread")]
+#[doc(hidden)]
+pub fn eval_ref<'a, P, E>(ext: &E) -> scoped::Insert<'a>
+where P: PluginRef<E>, P::Value: Send + Sync, E: SyncExtensible + ?Sized + 'a {
+    scoped::eval_ref::<P, E>(ext)
+}
+
+#[cfg(feature = "thread")]
+#[doc(hidden)]
+pub fn run_scoped<'a, E>(ext: &'a E, evals: &[fn(&E) -> scoped::Insert<'a>])
+where E: SyncExtensible + Sync + ?Sized {
+    scoped::run(ext, evals)
+}
+
+#[doc(hidden)]
+pub fn new_map() -> TypeMap {
+    TypeMap::new()
+}
+
+#[doc(hidden)]
+pub fn take_extensions<E: Extensible + ?Sized>(ext: &mut E) -> TypeMap {
+    mem::replace(ext.extensions_mut(), TypeMap::new())
+}
+
+#[doc(hidden)]
+pub fn peek_evaluated<P: Key, E: ExtensibleStore + ?Sized>(ext: &E) -> &P::Value {
+    lookup::find::<P, E>(ext).or_else(|| ext.store().get::<Fallback<P>>())
+        .expect("Plugin evaluated without being cached.")
+}
+
+#[doc(hidden)]
+pub fn take_or_eval<P: Plugin<E>, E: ExtensibleStore + ?Sized>(ext: &mut E) -> Result<P::Value, P::Error> {
+    match ext.store_mut().remove::<P>() {
+        Some(value) => Ok(value),
+        None => P::eval(ext)
+    }
+}
+
+#[doc(hidden)]
+pub fn invalidate<P: Key, E: Pluggable + ExtensibleStore + ?Sized>(ext: &mut E) {
+    ext.invalidate::<P>();
+}
+
+#[doc(hidden)]
+pub fn evict_all(map: &mut TypeMap) {
+    evict::evict_all(map)
+}
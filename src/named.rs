@@ -0,0 +1,39 @@
+//! Computing plugins addressed by name.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use typemap::{TypeMap, Key};
+
+use {Extensible, Plugin, Pluggable};
+
+// Evaluates a once-off instance of a plugin, boxing its value or error.
+type Compute<E> = fn(&mut E) -> Result<Box<dyn Any>, Box<dyn Any>>;
+
+struct Names<E: ?Sized>(PhantomData<E>);
+
+impl<E: ?Sized + 'static> Key for Names<E> {
+    type Value = HashMap<&'static str, Compute<E>>;
+}
+
+pub fn register<P, E>(map: &mut TypeMap, name: &'static str)
+where P: Plugin<E>, P::Error: Any, E: Pluggable + ?Sized + 'static {
+    map.entry::<Names<E>>().or_insert_with(HashMap::new)
+        .insert(name, compute::<P, E>);
+}
+
+pub fn compute_named<E>(ext: &mut E, name: &str) -> Option<Result<Box<dyn Any>, Box<dyn Any>>>
+where E: Extensible + ?Sized + 'static {
+    let compute = ext.extensions().get::<Names<E>>()
+        .and_then(|names| names.get(name)).cloned()?;
+    Some(compute(ext))
+}
+
+fn compute<P, E>(ext: &mut E) -> Result<Box<dyn Any>, Box<dyn Any>>
+where P: Plugin<E>, P::Error: Any, E: Pluggable + ?Sized {
+    match ext.compute::<P>() {
+        Ok(value) => Ok(Box::new(value)),
+        Err(err) => Err(Box::new(err))
+    }
+}
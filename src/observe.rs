@@ -0,0 +1,26 @@
+//! Observing every plugin evaluation.
+
+use std::any::TypeId;
+use std::time::{Duration, Instant};
+
+use typemap::Key;
+
+use {ExtensibleStore, ExtensionStore};
+
+pub struct Observer;
+
+impl Key for Observer { type Value = Box<dyn Fn(TypeId, Duration)>; }
+
+pub fn eval<P: Key, E: ExtensibleStore + ?Sized, R, F>(ext: &mut E, eval: F) -> R
+where F: FnOnce(&mut E) -> R {
+    if !ext.store().contains::<Observer>() { return eval(ext) }
+
+    let start = Instant::now();
+    let result = eval(ext);
+    let elapsed = start.elapsed();
+
+    if let Some(observer) = ext.store().get::<Observer>() {
+        observer(TypeId::of::<P>(), elapsed);
+    }
+    result
+}
@@ -0,0 +1,54 @@
+//! Falling back to a single parent's cache.
+
+use typemap::TypeMap;
+
+use {Extensible, Pluggable};
+
+/// An extensible type which may consult one parent's cache, as in
+/// request/sub-request designs.
+///
+/// `Pluggable`'s methods look for a plugin's value in the wrapper's own
+/// extensions, then in the parent's, and only evaluate the plugin against
+/// the wrapper if neither has it. The parent is never modified. For longer
+/// hierarchies, see `Chained`.
+///
+/// `get_mut`, and the methods which need a mutable value, such as
+/// `get_pinned_mut`, only consult the wrapper's own extensions, since the
+/// parent's values cannot be borrowed mutably.
+pub struct WithParent<'p, E: Extensible + ?Sized + 'p> {
+    parent: Option<&'p E>,
+    extensions: TypeMap,
+    clone_found: bool
+}
+
+impl<'p, E: Extensible + ?Sized + 'p> WithParent<'p, E> {
+    /// Create a wrapper with empty extensions and an optional parent.
+    ///
+    /// If `clone_found` is `true`, values found in the parent by methods
+    /// which return copies, such as `get`, are also cloned into the
+    /// wrapper's extensions. Otherwise, and for methods which return
+    /// references, such as `get_ref`, they are read from the parent each
+    /// time.
+    pub fn new(parent: Option<&'p E>, clone_found: bool) -> Self {
+        WithParent { parent, extensions: TypeMap::new(), clone_found }
+    }
+
+    /// Get the parent, if any.
+    pub fn parent(&self) -> Option<&'p E> {
+        self.parent
+    }
+}
+
+impl<'p, E: Extensible + ?Sized + 'p> Extensible for WithParent<'p, E> {
+    fn extensions(&self) -> &TypeMap { &self.extensions }
+
+    fn extensions_mut(&mut self) -> &mut TypeMap { &mut self.extensions }
+
+    fn parent_extensions(&self) -> Option<&TypeMap> {
+        self.parent.map(|parent| parent.extensions())
+    }
+
+    fn clones_from_parent(&self) -> bool { self.clone_found }
+}
+
+impl<'p, E: Extensible + ?Sized + 'p> Pluggable for WithParent<'p, E> {}
@@ -0,0 +1,57 @@
+//! Poisoning of plugins whose evaluation panicked.
+
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+
+use typemap::Key;
+
+use {ExtensibleStore, ExtensionStore};
+
+/// The error returned by `Pluggable::get_ref_or_poison`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoisonError<E> {
+    /// An evaluation of the plugin panicked, and the poison has not been
+    /// cleared with `Pluggable::clear_poison`.
+    Poisoned,
+
+    /// The plugin's evaluation failed.
+    Plugin(E)
+}
+
+impl<E: fmt::Display> fmt::Display for PoisonError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PoisonError::Poisoned => f.write_str("plugin poisoned by a panic during evaluation"),
+            PoisonError::Plugin(ref err) => err.fmt(f)
+        }
+    }
+}
+
+impl<E: Error> Error for PoisonError<E> {}
+
+pub struct Poison<P>(PhantomData<P>);
+
+impl<P: Key> Key for Poison<P> { type Value = (); }
+
+// Refuses to evaluate a poisoned plugin, as a poisoned `Mutex` refuses to
+// be locked.
+pub fn check<P: Key, E: ExtensibleStore + ?Sized>(ext: &E) {
+    if ext.store().contains::<Poison<P>>() {
+        panic!("plugin poisoned by a panic during evaluation");
+    }
+}
+
+// Evaluates the plugin through `eval`, poisoning it before resuming the
+// panic if the evaluation panics.
+pub fn eval<P: Key, E: ExtensibleStore + ?Sized, R, F>(ext: &mut E, eval: F) -> R
+where F: FnOnce(&mut E) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(|| eval(ext))) {
+        Ok(result) => result,
+        Err(payload) => {
+            ext.store_mut().insert::<Poison<P>>(());
+            panic::resume_unwind(payload)
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Background evaluation of plugins.
+
+use std::marker::PhantomData;
+use std::thread::{self, JoinHandle};
+
+use typemap::Key;
+
+use {ExtensibleStore, ExtensionStore, Plugin};
+
+/// Plugins which can be evaluated away from the extended type.
+///
+/// Prefetching splits evaluation in two: `input` runs on the calling thread
+/// and captures everything the plugin needs from the extended type, then
+/// `eval_input` runs on a background thread to produce the value.
+///
+/// `Plugin::eval` is usually implemented as `eval_input(input(ext))`.
+pub trait PrefetchPlugin<E: ?Sized>: Plugin<E> {
+    /// The data moved to the background thread.
+    type Input: Send + 'static;
+
+    /// Capture the input for a background evaluation.
+    fn input(ext: &mut E) -> Self::Input;
+
+    /// Create the plugin from a previously captured input.
+    fn eval_input(input: Self::Input) -> Result<Self::Value, Self::Error>;
+}
+
+struct InFlight<P>(PhantomData<P>);
+
+impl<P: Key> Key for InFlight<P> { type Value = Pending<P::Value>; }
+
+struct Refresh<P>(PhantomData<P>);
+
+impl<P: Key> Key for Refresh<P> { type Value = JoinHandle<Option<P::Value>>; }
+
+// Joins the background thread, returning its value if it succeeded.
+struct Pending<V>(Box<dyn FnOnce() -> Option<V>>);
+
+pub fn spawn<P, E>(ext: &mut E)
+where E: ExtensibleStore + ?Sized, P: PrefetchPlugin<E>,
+      P::Value: Send, P::Error: Send + 'static {
+    if ext.store().contains::<InFlight<P>>() { return }
+
+    let handle = run(P::eval_input, P::input(ext));
+    ext.store_mut().insert::<InFlight<P>>(pending(handle));
+}
+
+pub fn join<P: Key, S: ExtensionStore + ?Sized>(store: &mut S) -> Option<P::Value> {
+    store.remove::<InFlight<P>>().and_then(|Pending(join)| join())
+}
+
+pub fn spawn_refresh<P, E>(ext: &mut E)
+where E: ExtensibleStore + ?Sized, P: PrefetchPlugin<E>,
+      P::Value: Send, P::Error: 'static {
+    if ext.store().contains::<Refresh<P>>() { return }
+
+    let handle = run_ok(P::eval_input, P::input(ext));
+    ext.store_mut().insert::<Refresh<P>>(handle);
+}
+
+// Takes the value of a finished refresh, leaving a running one in place.
+pub fn finished_refresh<P: Key, S: ExtensionStore + ?Sized>(store: &mut S) -> Option<P::Value> {
+    if !store.get::<Refresh<P>>()?.is_finished() { return None }
+
+    store.remove::<Refresh<P>>()?.join().ok().and_then(|value| value)
+}
+
+fn run<I, T>(eval: fn(I) -> T, input: I) -> JoinHandle<T>
+where I: Send + 'static, T: Send + 'static {
+    thread::spawn(move || eval(input))
+}
+
+fn run_ok<I, V, Err>(eval: fn(I) -> Result<V, Err>, input: I) -> JoinHandle<Option<V>>
+where I: Send + 'static, V: Send + 'static, Err: 'static {
+    thread::spawn(move || eval(input).ok())
+}
+
+fn pending<V: 'static, Err: 'static>(handle: JoinHandle<Result<V, Err>>) -> Pending<V> {
+    Pending(Box::new(move || handle.join().ok().and_then(Result::ok)))
+}
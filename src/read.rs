@@ -0,0 +1,51 @@
+//! Reading cached values through shared references.
+
+use std::any::type_name;
+use std::error::Error;
+use std::fmt;
+
+use typemap::Key;
+
+use ExtensibleStore;
+use lookup;
+
+/// The error returned by `PluggableRead::get_cached` when a plugin has no
+/// cached value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NotCached {
+    /// The plugin's type name, from `std::any::type_name`.
+    pub plugin: &'static str
+}
+
+impl fmt::Display for NotCached {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "plugin `{}` is not cached", self.plugin)
+    }
+}
+
+impl Error for NotCached {}
+
+/// The read-only counterpart of `Pluggable`, for reading cached values
+/// through `&self`.
+///
+/// Nothing is ever evaluated through this trait, so it suits a two-phase
+/// discipline: first warm up every plugin the readers need through
+/// `Pluggable`, which requires `&mut self`, then hand out shared references
+/// to readers, which may run concurrently wherever the type can be shared.
+/// A reader asking for a plugin which was not warmed up gets `NotCached`.
+///
+/// Implemented for every type with extensions.
+pub trait PluggableRead {
+    /// Return a reference to the plugin's cached value, or `NotCached`.
+    ///
+    /// The value is looked up as by `Pluggable::get_ref`, including through
+    /// an alias, the handle of `get_shared` or a parent's extensions.
+    ///
+    /// `P` is the plugin type.
+    fn get_cached<P: Key>(&self) -> Result<&P::Value, NotCached>
+    where Self: ExtensibleStore {
+        lookup::find::<P, Self>(self).ok_or(NotCached { plugin: type_name::<P>() })
+    }
+}
+
+impl<T: ExtensibleStore + ?Sized> PluggableRead for T {}
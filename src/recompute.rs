@@ -0,0 +1,49 @@
+//! Recomputing every cached plugin at once.
+
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+
+use typemap::{TypeMap, Key};
+
+use {Extensible, Plugin, Pluggable};
+use {evict, lookup};
+
+// Re-evaluates a plugin if it is cached, returning its error on failure.
+type Recompute<E> = fn(&mut E) -> Result<(), Box<dyn Any>>;
+
+struct Recomputers<E: ?Sized>(PhantomData<E>);
+
+impl<E: ?Sized + 'static> Key for Recomputers<E> {
+    type Value = Vec<(TypeId, Recompute<E>)>;
+}
+
+pub fn register<P, E>(map: &mut TypeMap)
+where P: Plugin<E>, P::Error: Any, E: Extensible + Pluggable + ?Sized + 'static {
+    let recomputers = map.entry::<Recomputers<E>>().or_insert_with(Vec::new);
+
+    if !recomputers.iter().any(|&(id, _)| id == TypeId::of::<P>()) {
+        recomputers.push((TypeId::of::<P>(), recompute::<P, E>));
+    }
+}
+
+pub fn recompute_all<E>(ext: &mut E) -> Vec<(TypeId, Box<dyn Any>)>
+where E: Extensible + ?Sized + 'static {
+    let recomputers = match ext.extensions().get::<Recomputers<E>>() {
+        Some(recomputers) => recomputers.clone(),
+        None => return Vec::new()
+    };
+
+    recomputers.into_iter()
+        .filter_map(|(id, recompute)| recompute(ext).err().map(|err| (id, err)))
+        .collect()
+}
+
+fn recompute<P, E>(ext: &mut E) -> Result<(), Box<dyn Any>>
+where P: Plugin<E>, P::Error: Any, E: Extensible + Pluggable + ?Sized {
+    if !lookup::is_cached::<P, _>(ext.extensions()) { return Ok(()) }
+
+    let value = lookup::eval::<P, E>(ext).map_err(|err| Box::new(err) as Box<dyn Any>)?;
+    evict::discard::<P, _>(ext.extensions_mut());
+    lookup::store::<P, E>(ext, value);
+    Ok(())
+}
@@ -0,0 +1,24 @@
+//! Reporting which plugins are cached.
+
+use std::any::{self, TypeId};
+
+use typemap::{TypeMap, Key};
+
+struct Reported;
+
+impl Key for Reported { type Value = Vec<(TypeId, &'static str, fn(&TypeMap) -> bool)>; }
+
+pub fn register<P: Key>(map: &mut TypeMap) {
+    let reported = map.entry::<Reported>().or_insert_with(Vec::new);
+
+    if !reported.iter().any(|&(id, _, _)| id == TypeId::of::<P>()) {
+        reported.push((TypeId::of::<P>(), any::type_name::<P>(), TypeMap::contains::<P>));
+    }
+}
+
+pub fn report(map: &TypeMap) -> Vec<(&'static str, bool)> {
+    match map.get::<Reported>() {
+        Some(reported) => reported.iter().map(|&(_, name, cached)| (name, cached(map))).collect(),
+        None => Vec::new()
+    }
+}
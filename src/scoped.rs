@@ -0,0 +1,52 @@
+//! Evaluating independent plugins on scoped threads.
+
+use std::panic;
+use std::sync::{Arc, PoisonError};
+use std::thread;
+
+use typemap::{Key, ShareMap};
+
+use SyncExtensible;
+use sync::Shared;
+
+/// Plugins which can be evaluated from a shared reference to the extended
+/// type.
+///
+/// Such plugins can be evaluated in parallel by `warm_up_scoped!`. They are
+/// usually pure functions of the extended type, and `Plugin::eval` can be
+/// implemented as `eval_ref(ext)`.
+pub trait PluginRef<E: ?Sized>: Key {
+    /// The error type of a failed evaluation.
+    type Error;
+
+    /// Create the plugin from a shared reference to the extended type.
+    fn eval_ref(ext: &E) -> Result<Self::Value, Self::Error>;
+}
+
+/// Caches an evaluated value, if the evaluation succeeded.
+pub type Insert<'a> = Box<dyn FnOnce(&mut ShareMap) + Send + 'a>;
+
+pub fn eval_ref<'a, P, E>(ext: &E) -> Insert<'a>
+where P: PluginRef<E>, P::Value: Send + Sync, E: SyncExtensible + ?Sized + 'a {
+    let cached = ext.shared_extensions().read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .contains::<Shared<P>>();
+    let value = if cached { None } else { P::eval_ref(ext).ok() };
+
+    Box::new(move |map: &mut ShareMap| if let Some(value) = value {
+        map.entry::<Shared<P>>().or_insert_with(|| Arc::new(value));
+    })
+}
+
+pub fn run<'a, E>(ext: &'a E, evals: &[fn(&E) -> Insert<'a>])
+where E: SyncExtensible + Sync + ?Sized {
+    let inserts: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = evals.iter().map(|&eval| scope.spawn(move || eval(ext))).collect();
+        handles.into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|panic| panic::resume_unwind(panic)))
+            .collect()
+    });
+
+    let mut map = ext.shared_extensions().write().unwrap_or_else(PoisonError::into_inner);
+    for insert in inserts { insert(&mut map) }
+}
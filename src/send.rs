@@ -0,0 +1,84 @@
+//! Extensions which can be sent between threads.
+
+use typemap::{Key, SendMap};
+
+use Plugin;
+
+/// Defines an interface for extensible types whose extensions are `Send`.
+///
+/// Backing a type's extensions with a `SendMap` allows moving it to another
+/// thread, for instance handing it to a thread pool, while keeping its
+/// cached plugin values. In exchange, every plugin value must be `Send`.
+pub trait SendExtensible {
+    /// Get a reference to the type's extension storage.
+    fn extensions(&self) -> &SendMap;
+
+    /// Get a mutable reference to the type's extension storage.
+    fn extensions_mut(&mut self) -> &mut SendMap;
+}
+
+/// An interface for plugins that cache values in `Send` extensions.
+///
+/// The methods mirror the core caching methods of `Pluggable`, with plugin
+/// values bounded by `Send`. The surface is deliberately smaller: methods
+/// which keep registries or other side entries in the extensions, such as
+/// eviction hooks and fallbacks, are only available through `Pluggable`.
+pub trait SendPluggable {
+    /// Return a copy of the plugin's produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already.
+    /// If plugin creation fails, an error is returned.
+    ///
+    /// `P` is the plugin type.
+    fn get<P: Plugin<Self>>(&mut self) -> Result<P::Value, P::Error>
+    where P::Value: Clone + Send, Self: SendExtensible {
+        self.get_ref::<P>().cloned()
+    }
+
+    /// Return a reference to the plugin's produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already.
+    /// If plugin creation fails an error is returned.
+    ///
+    /// `P` is the plugin type.
+    fn get_ref<P: Plugin<Self>>(&mut self) -> Result<&P::Value, P::Error>
+    where P::Value: Send, Self: SendExtensible {
+        self.get_mut::<P>().map(|mutref| &*mutref)
+    }
+
+    /// Return a mutable reference to the plugin's produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already.
+    /// If plugin creation fail an error is returned.
+    ///
+    /// `P` is the plugin type.
+    fn get_mut<P: Plugin<Self>>(&mut self) -> Result<&mut P::Value, P::Error>
+    where P::Value: Send, Self: SendExtensible {
+        if self.extensions().contains::<P>() {
+            return Ok(self.extensions_mut().get_mut::<P>().unwrap());
+        }
+
+        P::eval(self).map(move |data| self.extensions_mut().entry::<P>().or_insert_with(|| data))
+    }
+
+    /// Create and evaluate a once-off instance of a plugin.
+    fn compute<P: Plugin<Self>>(&mut self) -> Result<P::Value, P::Error> {
+        <P as Plugin<Self>>::eval(self)
+    }
+
+    /// Cache a value for `P` without evaluating the plugin.
+    ///
+    /// Returns the previously cached value, if any.
+    fn insert<P: Key>(&mut self, value: P::Value) -> Option<P::Value>
+    where P::Value: Send, Self: SendExtensible {
+        self.extensions_mut().insert::<P>(value)
+    }
+
+    /// Remove and return the plugin's cached value, without evaluating it.
+    ///
+    /// The plugin is evaluated again the next time it is requested.
+    fn take<P: Key>(&mut self) -> Option<P::Value>
+    where P::Value: Send, Self: SendExtensible {
+        self.extensions_mut().remove::<P>()
+    }
+}
@@ -0,0 +1,17 @@
+//! Recording plugin errors on the extended type.
+
+use std::any::Any;
+
+use Extensible;
+
+/// Extensible types which collect the errors of failed plugin evaluations,
+/// as in pipelines where one failing plugin shouldn't abort the rest.
+///
+/// `Pluggable::get_or_record` passes evaluation errors to `record_error`
+/// instead of returning them.
+pub trait ErrorSink: Extensible {
+    /// Record the error of a failed plugin evaluation.
+    ///
+    /// The error is the plugin's boxed `Plugin::Error`.
+    fn record_error(&mut self, err: Box<dyn Any>);
+}
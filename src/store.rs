@@ -0,0 +1,127 @@
+//! Storage backends for cached plugin values.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use typemap::{TypeMap, Key};
+
+use Extensible;
+
+/// A type-keyed store of plugin values.
+///
+/// Implemented for `TypeMap`, the default storage, and for a plain
+/// `HashMap<TypeId, Box<dyn Any>>` keyed by the `TypeId` of each key type.
+pub trait ExtensionStore {
+    /// Check if a key has an associated value in the store.
+    fn contains<K: Key>(&self) -> bool;
+
+    /// Get a reference to the value associated with a key.
+    fn get<K: Key>(&self) -> Option<&K::Value>;
+
+    /// Get a mutable reference to the value associated with a key.
+    fn get_mut<K: Key>(&mut self) -> Option<&mut K::Value>;
+
+    /// Associate a value with a key, returning the previous value.
+    fn insert<K: Key>(&mut self, value: K::Value) -> Option<K::Value>;
+
+    /// Remove the value associated with a key.
+    fn remove<K: Key>(&mut self) -> Option<K::Value>;
+
+    /// Get a mutable reference to the value associated with a key,
+    /// inserting the result of `default` if the key is vacant.
+    fn entry_or_insert_with<K: Key, F>(&mut self, default: F) -> &mut K::Value
+    where F: FnOnce() -> K::Value;
+}
+
+impl ExtensionStore for TypeMap {
+    fn contains<K: Key>(&self) -> bool { TypeMap::contains::<K>(self) }
+
+    fn get<K: Key>(&self) -> Option<&K::Value> { TypeMap::get::<K>(self) }
+
+    fn get_mut<K: Key>(&mut self) -> Option<&mut K::Value> { TypeMap::get_mut::<K>(self) }
+
+    fn insert<K: Key>(&mut self, value: K::Value) -> Option<K::Value> {
+        TypeMap::insert::<K>(self, value)
+    }
+
+    fn remove<K: Key>(&mut self) -> Option<K::Value> { TypeMap::remove::<K>(self) }
+
+    fn entry_or_insert_with<K: Key, F>(&mut self, default: F) -> &mut K::Value
+    where F: FnOnce() -> K::Value {
+        self.entry::<K>().or_insert_with(default)
+    }
+}
+
+impl ExtensionStore for HashMap<TypeId, Box<dyn Any>> {
+    fn contains<K: Key>(&self) -> bool {
+        self.contains_key(&TypeId::of::<K>())
+    }
+
+    fn get<K: Key>(&self) -> Option<&K::Value> {
+        HashMap::get(self, &TypeId::of::<K>()).and_then(|value| value.downcast_ref())
+    }
+
+    fn get_mut<K: Key>(&mut self) -> Option<&mut K::Value> {
+        HashMap::get_mut(self, &TypeId::of::<K>()).and_then(|value| value.downcast_mut())
+    }
+
+    fn insert<K: Key>(&mut self, value: K::Value) -> Option<K::Value> {
+        HashMap::insert(self, TypeId::of::<K>(), Box::new(value))
+            .and_then(|old| old.downcast().ok()).map(|old| *old)
+    }
+
+    fn remove<K: Key>(&mut self) -> Option<K::Value> {
+        HashMap::remove(self, &TypeId::of::<K>())
+            .and_then(|old| old.downcast().ok()).map(|old| *old)
+    }
+
+    fn entry_or_insert_with<K: Key, F>(&mut self, default: F) -> &mut K::Value
+    where F: FnOnce() -> K::Value {
+        self.entry(TypeId::of::<K>())
+            .or_insert_with(|| Box::new(default()))
+            .downcast_mut()
+            .expect("Value stored under a key does not have the key's value type.")
+    }
+}
+
+/// Defines an interface for extensible types with any `ExtensionStore`.
+///
+/// This is implemented for every `Extensible` type, using its `TypeMap`.
+/// Implement it directly to back a type's extensions with another store.
+///
+/// The core caching methods of `Pluggable` work with any store; methods
+/// which keep registries in the extensions or inspect the whole map, such
+/// as `collect_as`, require `Extensible`.
+pub trait ExtensibleStore {
+    /// The type's extension storage.
+    type Store: ExtensionStore + 'static;
+
+    /// Get a reference to the type's extension storage.
+    fn store(&self) -> &Self::Store;
+
+    /// Get a mutable reference to the type's extension storage.
+    fn store_mut(&mut self) -> &mut Self::Store;
+
+    /// Get a reference to a parent's extension storage, whose cached values
+    /// are returned before a plugin is evaluated against this type.
+    ///
+    /// There is no parent by default.
+    fn parent_store(&self) -> Option<&Self::Store> { None }
+
+    /// Whether the methods which return copies of cached values, such as
+    /// `get`, also clone values found in `parent_store` into this type's
+    /// own storage.
+    fn clones_from_parent_store(&self) -> bool { false }
+}
+
+impl<T: Extensible + ?Sized> ExtensibleStore for T {
+    type Store = TypeMap;
+
+    fn store(&self) -> &TypeMap { self.extensions() }
+
+    fn store_mut(&mut self) -> &mut TypeMap { self.extensions_mut() }
+
+    fn parent_store(&self) -> Option<&TypeMap> { self.parent_extensions() }
+
+    fn clones_from_parent_store(&self) -> bool { self.clones_from_parent() }
+}
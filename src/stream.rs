@@ -0,0 +1,79 @@
+//! Plugins which produce a sequence of values.
+
+use std::marker::PhantomData;
+use std::vec;
+
+use typemap::Key;
+
+use {ExtensibleStore, ExtensionStore};
+
+/// Plugins which yield their values incrementally, such as the lines of a
+/// file or the pages of an API.
+///
+/// The plugin's `Key::Value` is the type of each item.
+///
+/// These plugins are evaluated by `Pluggable::stream`. If `CACHE` is
+/// `true`, the sequence is cached once a stream has been drained without
+/// errors, and later streams replay the cached items instead of evaluating
+/// the plugin again. Streams which are dropped before the end, or which
+/// yield an error, cache nothing.
+pub trait StreamingPlugin<E: ?Sized>: Key {
+    /// The error type of a failed item.
+    type Error;
+
+    /// The stream produced by an evaluation.
+    type Stream: Iterator<Item = Result<Self::Value, Self::Error>>;
+
+    /// Whether to cache a fully drained sequence.
+    const CACHE: bool = false;
+
+    /// Start producing the plugin's items.
+    fn eval(ext: &mut E) -> Self::Stream;
+}
+
+/// The iterator returned by `Pluggable::stream`.
+pub struct Stream<'a, P: StreamingPlugin<E>, E: ?Sized + 'a>(State<'a, P, E>);
+
+enum State<'a, P: StreamingPlugin<E>, E: ?Sized + 'a> {
+    Cached(vec::IntoIter<P::Value>),
+    Live { stream: P::Stream, seen: Option<Vec<P::Value>>, ext: &'a mut E }
+}
+
+impl<'a, P, E> Iterator for Stream<'a, P, E>
+where P: StreamingPlugin<E>, P::Value: Clone, E: ExtensibleStore + ?Sized + 'a {
+    type Item = Result<P::Value, P::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0 {
+            State::Cached(ref mut items) => items.next().map(Ok),
+            State::Live { ref mut stream, ref mut seen, ref mut ext } => match stream.next() {
+                Some(Ok(item)) => {
+                    if let Some(ref mut seen) = *seen { seen.push(item.clone()); }
+                    Some(Ok(item))
+                },
+                Some(Err(err)) => { *seen = None; Some(Err(err)) },
+                None => {
+                    if let Some(items) = seen.take() {
+                        ext.store_mut().insert::<Drained<P>>(items);
+                    }
+                    None
+                }
+            }
+        }
+    }
+}
+
+pub fn stream<P, E>(ext: &mut E) -> Stream<'_, P, E>
+where P: StreamingPlugin<E>, P::Value: Clone, E: ExtensibleStore + ?Sized {
+    if let Some(items) = ext.store().get::<Drained<P>>() {
+        return Stream(State::Cached(items.clone().into_iter()));
+    }
+
+    let stream = P::eval(ext);
+    let seen = if P::CACHE { Some(Vec::new()) } else { None };
+    Stream(State::Live { stream, seen, ext })
+}
+
+struct Drained<P>(PhantomData<P>);
+
+impl<P: Key> Key for Drained<P> { type Value = Vec<P::Value>; }
@@ -0,0 +1,59 @@
+//! Extensions shared between clones of an extended type.
+
+use std::marker::PhantomData;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use typemap::{Key, ShareMap};
+
+use Plugin;
+
+/// Defines an interface for extensible types whose extensions are shared,
+/// through an `Arc`, by every clone of the type.
+///
+/// Such types are cheap to clone, and a plugin evaluated through one clone
+/// is cached for all of them.
+pub trait SyncExtensible {
+    /// Get a reference to the type's shared extension storage.
+    fn shared_extensions(&self) -> &Arc<RwLock<ShareMap>>;
+}
+
+/// An interface for plugins that cache values in shared extensions.
+pub trait SyncPluggable {
+    /// Return a shared handle to the plugin's produced value.
+    ///
+    /// The plugin will be created if it doesn't exist already. Cached values
+    /// are read under the read lock on the shared extensions, so concurrent
+    /// cache hits don't serialize. On a miss, the write lock is taken and
+    /// the cache checked again before evaluating, so when several clones
+    /// race on the same uncached plugin, it is only evaluated once and every
+    /// clone receives the same `Arc`. If plugin creation fails, an error is
+    /// returned and nothing is cached.
+    ///
+    /// Because the write lock is held during evaluation, a plugin evaluated
+    /// this way must not call `get_arc` on the same extensions: doing so
+    /// deadlocks.
+    ///
+    /// `P` is the plugin type.
+    fn get_arc<P: Plugin<Self>>(&mut self) -> Result<Arc<P::Value>, P::Error>
+    where P::Value: Send + Sync, Self: SyncExtensible {
+        let shared = self.shared_extensions().clone();
+        // Values are only inserted once fully evaluated, so the map is
+        // consistent even if an evaluation panicked while holding the lock.
+        if let Some(value) = shared.read().unwrap_or_else(PoisonError::into_inner).get::<Shared<P>>() {
+            return Ok(value.clone());
+        }
+
+        let mut map = shared.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = map.get::<Shared<P>>() {
+            return Ok(value.clone());
+        }
+
+        let value = Arc::new(P::eval(self)?);
+        map.insert::<Shared<P>>(value.clone());
+        Ok(value)
+    }
+}
+
+pub struct Shared<P>(PhantomData<P>);
+
+impl<P: Key> Key for Shared<P> { type Value = Arc<P::Value>; }
@@ -0,0 +1,102 @@
+//! Utilities for testing plugins.
+
+use std::any::TypeId;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use typemap::Key;
+
+use {Extensible, Plugin, Pluggable};
+
+#[doc(hidden)]
+pub struct EvalCount<P>(PhantomData<P>);
+
+impl<P: Key> Key for EvalCount<P> { type Value = usize; }
+
+/// Assert that a set of plugins produces the same values regardless of the
+/// order in which they are evaluated.
+///
+/// `assert_order_independent!(factory, [A, B, C])` evaluates the listed
+/// plugins in every permutation, each time against a fresh context created
+/// by calling `factory`, and asserts that the resulting `Result`s are equal
+/// across all orderings. This catches plugins which accidentally depend on
+/// evaluation order through shared mutable state.
+///
+/// Plugin values and errors must implement `Clone`, `PartialEq` and `Debug`.
+/// Every permutation is tried, so keep the list short.
+#[macro_export]
+macro_rules! assert_order_independent {
+    ($factory:expr, [$($plugin:ty),+ $(,)*]) => {{
+        let factory = $factory;
+        let evals: &[fn(&mut _)] = &[$($crate::test_util::eval::<$plugin, _>),+];
+        let mut expected = None;
+
+        for order in $crate::test_util::permutations(evals.len()) {
+            let mut ctx = factory();
+            for &i in &order { evals[i](&mut ctx); }
+            let values = ($($crate::Pluggable::get::<$plugin>(&mut ctx),)+);
+
+            match expected {
+                None => expected = Some((order, values)),
+                Some((ref first, ref first_values)) => assert!(
+                    *first_values == values,
+                    "plugins evaluated in order {:?} produced {:?}, but order {:?} produced {:?}",
+                    first, first_values, order, values
+                )
+            }
+        }
+    }}
+}
+
+/// Assert that exactly the listed plugins were newly cached since a
+/// checkpoint taken with `Pluggable::checkpoint`.
+///
+/// `assert_computed_since!(ctx, checkpoint, [A, B])` compares the keys now
+/// stored in `ctx`'s extensions with those in `checkpoint`, and panics
+/// unless the new keys are exactly those of `A` and `B`. This locks in the
+/// evaluation footprint of a code path. Keys the crate stores internally,
+/// such as registrations made since the checkpoint, also count as new.
+#[macro_export]
+macro_rules! assert_computed_since {
+    ($ctx:expr, $checkpoint:expr, [$($plugin:ty),* $(,)*]) => {{
+        let computed = $crate::test_util::computed_since(&$ctx, &$checkpoint);
+        let expected = $crate::test_util::type_ids(&[$(::std::any::TypeId::of::<$plugin>()),*]);
+        assert!(
+            computed == expected,
+            "expected exactly {:?} to be computed, but {} new keys were computed",
+            [$(stringify!($plugin)),*], computed.len()
+        );
+    }}
+}
+
+#[doc(hidden)]
+pub fn computed_since<E>(ext: &E, checkpoint: &HashSet<TypeId>) -> HashSet<TypeId>
+where E: Extensible + Pluggable {
+    ext.checkpoint().difference(checkpoint).cloned().collect()
+}
+
+#[doc(hidden)]
+pub fn type_ids(ids: &[TypeId]) -> HashSet<TypeId> {
+    ids.iter().cloned().collect()
+}
+
+#[doc(hidden)]
+pub fn eval<P, E>(ext: &mut E)
+where P: Plugin<E>, E: Extensible + Pluggable {
+    let _ = ext.get_ref::<P>();
+}
+
+#[doc(hidden)]
+pub fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 { return vec![Vec::new()] }
+
+    let mut all = Vec::new();
+    for rest in permutations(n - 1) {
+        for position in 0..n {
+            let mut order = rest.clone();
+            order.insert(position, n - 1);
+            all.push(order);
+        }
+    }
+    all
+}
@@ -0,0 +1,13 @@
+//! Ambient trace context for plugin evaluations.
+
+use typemap::Key;
+
+/// The key under which `Pluggable::set_trace_id` stores the correlation id
+/// of the work a context belongs to.
+///
+/// This key is reserved: plugins read the id through
+/// `Pluggable::current_trace_id` and should not store anything else under
+/// it. Like registrations, the id is removed by `clear_extensions`.
+pub struct TraceId;
+
+impl Key for TraceId { type Value = String; }
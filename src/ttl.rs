@@ -0,0 +1,66 @@
+//! Plugins whose cached values expire.
+
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+use typemap::{TypeMap, Key};
+
+use Plugin;
+use evict;
+
+/// Plugins whose cached values expire some time after evaluation.
+///
+/// Evaluated through `Pluggable::get_ttl`, the plugin's value is cached
+/// together with the time it was evaluated, and evaluated again once `ttl`
+/// has passed. Expired values can also be removed in bulk with
+/// `Pluggable::sweep_expired`.
+pub trait TtlPlugin<E: ?Sized>: Plugin<E> {
+    /// How long a newly evaluated value stays fresh.
+    fn ttl() -> Duration;
+}
+
+pub struct CachedAt<P>(PhantomData<P>);
+
+impl<P: Key> Key for CachedAt<P> { type Value = Instant; }
+
+// Removes a plugin's cached values if they have expired by the given time.
+type Sweep = fn(&mut TypeMap, Instant) -> bool;
+
+struct Managed;
+
+impl Key for Managed { type Value = Vec<(TypeId, Sweep)>; }
+
+pub fn register<P: TtlPlugin<E>, E: ?Sized>(map: &mut TypeMap) {
+    let managed = map.entry::<Managed>().or_insert_with(Vec::new);
+
+    if !managed.iter().any(|&(id, _)| id == TypeId::of::<P>()) {
+        managed.push((TypeId::of::<P>(), sweep::<P, E>));
+    }
+}
+
+pub fn is_expired<P: TtlPlugin<E>, E: ?Sized>(map: &TypeMap, now: Instant) -> bool {
+    is_older_than::<P>(map, P::ttl(), now)
+}
+
+// Whether the plugin's value was cached with a time at least `ttl` before
+// `now`. Values cached without a time never are.
+pub fn is_older_than<P: Key>(map: &TypeMap, ttl: Duration, now: Instant) -> bool {
+    map.get::<CachedAt<P>>().is_some_and(|&at| {
+        now.checked_duration_since(at).is_some_and(|age| age >= ttl)
+    })
+}
+
+pub fn sweep_expired(map: &mut TypeMap, now: Instant) -> usize {
+    match map.get::<Managed>().cloned() {
+        Some(managed) => managed.into_iter().filter(|&(_, sweep)| sweep(map, now)).count(),
+        None => 0
+    }
+}
+
+fn sweep<P: TtlPlugin<E>, E: ?Sized>(map: &mut TypeMap, now: Instant) -> bool {
+    if !is_expired::<P, E>(map, now) { return false }
+
+    map.remove::<CachedAt<P>>();
+    evict::discard::<P, _>(map).is_some()
+}
@@ -0,0 +1,52 @@
+//! Generation stamps recorded for cached plugin values.
+
+use std::marker::PhantomData;
+
+use typemap::Key;
+
+use ExtensionStore;
+
+/// Plugins whose cached values are recomputed once the generation advances.
+///
+/// Implementing this marker opts a plugin in; it must also be registered
+/// with `Pluggable::register_generation_sensitive`. Once registered, `get`
+/// and the methods built on it discard a value computed at an earlier
+/// generation and evaluate the plugin again.
+pub trait GenerationSensitive: Key {}
+
+pub struct Generation;
+
+impl Key for Generation { type Value = u64; }
+
+pub struct Stamp<P>(PhantomData<P>);
+
+impl<P: Key> Key for Stamp<P> { type Value = u64; }
+
+// Stamps a freshly computed value with the current generation, if the
+// generation counter has been started.
+pub fn stamp<P: Key, S: ExtensionStore + ?Sized>(store: &mut S) {
+    if let Some(&generation) = store.get::<Generation>() {
+        store.insert::<Stamp<P>>(generation);
+    }
+}
+
+// Forgets the generation a plugin's value was computed at, when the value
+// is replaced or removed other than by evaluating the plugin.
+pub fn clear<P: Key, S: ExtensionStore + ?Sized>(store: &mut S) {
+    store.remove::<Stamp<P>>();
+}
+
+struct Sensitive<P>(PhantomData<P>);
+
+impl<P: Key> Key for Sensitive<P> { type Value = (); }
+
+pub fn register<P: GenerationSensitive, S: ExtensionStore + ?Sized>(store: &mut S) {
+    store.insert::<Sensitive<P>>(());
+}
+
+// Whether a registered plugin's cached value was computed before the
+// current generation. Values cached without a stamp count as generation 0.
+pub fn is_stale<P: Key, S: ExtensionStore + ?Sized>(store: &S) -> bool {
+    store.contains::<Sensitive<P>>() && store.contains::<P>() &&
+        store.get::<Stamp<P>>().cloned().unwrap_or(0) != store.get::<Generation>().cloned().unwrap_or(0)
+}